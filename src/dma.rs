@@ -0,0 +1,211 @@
+//! Enhanced Direct Memory Access (eDMA)
+//!
+//! This module constrains the eDMA controller together with its channel multiplexer
+//! (DMAMUX). Each of the 16 channels is an owned type, which peripheral drivers
+//! like [`serial`](crate::serial) route to their DMA requests.
+//!
+//! Ref: Reference Manual, Chapter 15 and Chapter 16
+
+use crate::pac;
+use crate::pcc::{self, EnableError};
+use core::ptr;
+
+/// Maximum number of words in a single transfer
+pub const MAX_TRANSFER_LEN: usize = 0x7FFF;
+
+/// Extension trait to split the eDMA peripheral into independent channels
+pub trait DmaExt {
+    /// The type to split the eDMA into
+    type Parts;
+
+    /// Splits the eDMA and DMAMUX peripherals into independent channels
+    ///
+    /// Clocks of both peripherals are enabled; it's possible to have errors because
+    /// they may be in use by another core.
+    fn split(
+        self,
+        _dmamux: pac::DMAMUX0,
+        pcc_dma: &mut pcc::DMA0,
+        pcc_dmamux: &mut pcc::DMAMUX0,
+    ) -> Result<Self::Parts, EnableError>;
+}
+
+/// DMA channel - DO NOT IMPLEMENT THIS TRAIT
+///
+/// # Safety
+///
+/// Implemented by this crate for owned DMA channels only.
+pub unsafe trait Channel {
+    #[doc(hidden)]
+    const NUMBER: usize;
+}
+
+macro_rules! dma_impl {
+    ($($CHi: ident: ($chi: ident, $i: expr),)+) => {
+impl DmaExt for pac::DMA0 {
+    type Parts = Channels;
+
+    fn split(
+        self,
+        _dmamux: pac::DMAMUX0,
+        pcc_dma: &mut pcc::DMA0,
+        pcc_dmamux: &mut pcc::DMAMUX0,
+    ) -> Result<Channels, EnableError> {
+        pcc_dma.try_enable()?;
+        if let Err(e) = pcc_dmamux.try_enable() {
+            pcc_dma.disable();
+            return Err(e);
+        }
+        Ok(Channels {
+            $( $chi: $CHi { _ownership: () }, )+
+        })
+    }
+}
+
+/// DMA channels
+pub struct Channels {
+    $(
+        #[doc = "DMA channel"]
+        pub $chi: $CHi,
+    )+
+}
+
+impl Channels {
+    /// Free and release the eDMA and DMAMUX peripherals
+    pub fn free(self, pcc_dma: &mut pcc::DMA0, pcc_dmamux: &mut pcc::DMAMUX0) -> (pac::DMA0, pac::DMAMUX0) {
+        use core::mem::transmute;
+        // disable peripheral clocks
+        pcc_dmamux.disable();
+        pcc_dma.disable();
+        // return the ownership of peripherals
+        unsafe { (transmute::<(), pac::DMA0>(()), transmute::<(), pac::DMAMUX0>(())) }
+    }
+}
+
+$(
+    #[doc = "DMA channel"]
+    pub struct $CHi {
+        _ownership: (),
+    }
+
+    unsafe impl Channel for $CHi {
+        const NUMBER: usize = $i;
+    }
+)+
+    };
+}
+
+dma_impl! {
+    C0: (ch0, 0),
+    C1: (ch1, 1),
+    C2: (ch2, 2),
+    C3: (ch3, 3),
+    C4: (ch4, 4),
+    C5: (ch5, 5),
+    C6: (ch6, 6),
+    C7: (ch7, 7),
+    C8: (ch8, 8),
+    C9: (ch9, 9),
+    C10: (ch10, 10),
+    C11: (ch11, 11),
+    C12: (ch12, 12),
+    C13: (ch13, 13),
+    C14: (ch14, 14),
+    C15: (ch15, 15),
+}
+
+// Transfer Control Descriptor; every channel has the same layout, while the PAC
+// has distinct register types for each channel.
+#[repr(C)]
+struct Tcd {
+    saddr: u32,
+    soff: u16,
+    attr: u16,
+    nbytes: u32,
+    slast: u32,
+    daddr: u32,
+    doff: u16,
+    citer: u16,
+    dlastsga: u32,
+    csr: u16,
+    biter: u16,
+}
+
+const TCD_CSR_DREQ: u16 = 1 << 3;
+const TCD_CSR_DONE: u16 = 1 << 7;
+
+fn tcd(channel: usize) -> *mut Tcd {
+    (pac::DMA0::ptr() as usize + 0x1000 + 0x20 * channel) as *mut Tcd
+}
+
+/// One byte transfer direction and addresses of a channel
+pub(crate) struct Transfer {
+    pub source: u32,
+    pub source_offset: u16,
+    pub destination: u32,
+    pub destination_offset: u16,
+    pub len: usize,
+    /// Restart from the first word after the last one instead of stopping
+    pub circular: bool,
+}
+
+/// Routes a peripheral request to the channel and loads its descriptor
+pub(crate) fn configure(channel: usize, request: u8, transfer: Transfer) {
+    assert!(transfer.len > 0 && transfer.len <= MAX_TRANSFER_LEN, "DMA transfer length");
+    let dma = unsafe { &*pac::DMA0::ptr() };
+    let dmamux = unsafe { &*pac::DMAMUX0::ptr() };
+    // note(unsafe): channel number is less than 16
+    dma.cerq.write(|w| unsafe { w.cerq().bits(channel as u8) });
+    dmamux.chcfg[channel].write(|w| unsafe { w.bits(0) });
+    let len = transfer.len as u16;
+    let (slast, dlastsga) = match transfer.circular {
+        // rewind the address advanced by the whole major loop
+        true => ((-(transfer.len as i32 * transfer.source_offset as i32)) as u32,
+            (-(transfer.len as i32 * transfer.destination_offset as i32)) as u32),
+        false => (0, 0),
+    };
+    let tcd = tcd(channel);
+    // note(unsafe): the owner of the channel has exclusive access to its descriptor
+    unsafe {
+        ptr::addr_of_mut!((*tcd).csr).write_volatile(0);
+        ptr::addr_of_mut!((*tcd).saddr).write_volatile(transfer.source);
+        ptr::addr_of_mut!((*tcd).soff).write_volatile(transfer.source_offset);
+        ptr::addr_of_mut!((*tcd).attr).write_volatile(0); // 8-bit source and destination
+        ptr::addr_of_mut!((*tcd).nbytes).write_volatile(1);
+        ptr::addr_of_mut!((*tcd).slast).write_volatile(slast);
+        ptr::addr_of_mut!((*tcd).daddr).write_volatile(transfer.destination);
+        ptr::addr_of_mut!((*tcd).doff).write_volatile(transfer.destination_offset);
+        ptr::addr_of_mut!((*tcd).citer).write_volatile(len);
+        ptr::addr_of_mut!((*tcd).dlastsga).write_volatile(dlastsga);
+        ptr::addr_of_mut!((*tcd).biter).write_volatile(len);
+        // a single shot transfer clears its request at the end of major loop
+        let csr = if transfer.circular { 0 } else { TCD_CSR_DREQ };
+        ptr::addr_of_mut!((*tcd).csr).write_volatile(csr);
+    }
+    dmamux.chcfg[channel].write(|w| unsafe { w.source().bits(request).enbl().set_bit() });
+}
+
+/// Enables the hardware request of the channel
+pub(crate) fn start(channel: usize) {
+    let dma = unsafe { &*pac::DMA0::ptr() };
+    dma.serq.write(|w| unsafe { w.serq().bits(channel as u8) });
+}
+
+/// Disables the hardware request and routing of the channel
+pub(crate) fn stop(channel: usize) {
+    let dma = unsafe { &*pac::DMA0::ptr() };
+    let dmamux = unsafe { &*pac::DMAMUX0::ptr() };
+    dma.cerq.write(|w| unsafe { w.cerq().bits(channel as u8) });
+    dmamux.chcfg[channel].write(|w| unsafe { w.bits(0) });
+    dma.cdne.write(|w| unsafe { w.cdne().bits(channel as u8) });
+}
+
+/// Returns `true` if the major loop of the channel has completed
+pub(crate) fn is_done(channel: usize) -> bool {
+    unsafe { ptr::addr_of!((*tcd(channel)).csr).read_volatile() & TCD_CSR_DONE != 0 }
+}
+
+/// Number of words left in the current major loop
+pub(crate) fn remaining(channel: usize) -> usize {
+    unsafe { (ptr::addr_of!((*tcd(channel)).citer).read_volatile() & 0x7FFF) as usize }
+}
@@ -1,8 +1,26 @@
 //! General-Purpose Input/Output (GPIO)
+//!
+//! # Waiting for pin events
+//!
+//! Input pins provide futures that wait for a level or an edge, backed by the PORT pin
+//! interrupt. To drive these futures, enable the PORTx interrupts and call the matching
+//! function from the handler:
+//!
+//! ```
+//! #[no_mangle]
+//! fn PORTA_IRQHandler() {
+//!     rv32m1_ri5cy_hal::gpio::on_porta_interrupt();
+//! }
+//! ```
 
 use crate::port::*;
+use core::cell::RefCell;
+use core::future::Future;
 use core::marker::PhantomData;
 use core::convert::Infallible;
+use core::pin::Pin;
+use core::task::{Context, Poll, Waker};
+use riscv::interrupt::Mutex;
 use embedded_hal::digital::{OutputPin, StatefulOutputPin, ToggleableOutputPin, InputPin};
 
 /// Gpio wrapper
@@ -58,6 +76,41 @@ pub struct PushPull;
 /// Open-drain output (type state)
 pub struct OpenDrain;
 
+/// Digital output pin state
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum PinState {
+    /// Low pin state
+    Low,
+    /// High pin state
+    High,
+}
+
+impl From<bool> for PinState {
+    fn from(value: bool) -> PinState {
+        match value {
+            false => PinState::Low,
+            true => PinState::High,
+        }
+    }
+}
+
+/// Runtime switchable input and output, for the GPIO function (type state)
+///
+/// Pin direction and pull resistor can be changed through `&mut self` while the
+/// pin stays owned, which suits bit-banged bidirectional protocols like 1-Wire.
+pub struct Flex;
+
+/// Internal pull resistor configuration, valid in all digital pin muxing modes.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Pull {
+    /// Internal pull resistor is disabled.
+    None,
+    /// Internal pull-up resistor is enabled.
+    Up,
+    /// Internal pull-down resistor is enabled.
+    Down,
+}
+
 /// Port pin that is configured into ALT1 function
 ///
 /// This trait should only be implemented by this HAL crate; user should not implement this trait.
@@ -80,11 +133,34 @@ pub trait Alt1Pin {
     #[doc(hidden)]
     fn set_high(&self);
     #[doc(hidden)]
+    fn set_state(&self, state: PinState) {
+        match state {
+            PinState::Low => self.set_low(),
+            PinState::High => self.set_high(),
+        }
+    }
+    #[doc(hidden)]
     fn is_set_high(&self) -> bool;
     #[doc(hidden)]
     fn toggle(&self);
     #[doc(hidden)]
     fn is_high(&self) -> bool;
+    #[doc(hidden)]
+    fn set_direction_output(&self);
+    #[doc(hidden)]
+    fn set_direction_input(&self);
+    #[doc(hidden)]
+    fn is_direction_output(&self) -> bool;
+    #[doc(hidden)]
+    fn set_pull(&self, pull: Pull);
+    #[doc(hidden)]
+    fn set_open_drain(&self, value: bool);
+    #[doc(hidden)]
+    fn set_interrupt(&self, irqc: u8);
+    #[doc(hidden)]
+    fn interrupt_config(&self) -> u8;
+    #[doc(hidden)]
+    fn interrupt_index(&self) -> (usize, usize);
 }
 
 impl<PIN: Alt1Pin, MODE> Gpio<PIN, MODE> {
@@ -92,10 +168,26 @@ impl<PIN: Alt1Pin, MODE> Gpio<PIN, MODE> {
     pub fn into_push_pull_output(self, gpio: &mut PIN::Gpio) -> Gpio<PIN, Output<PushPull>> {
         Gpio { pin: self.pin.configure_push_pull_output(gpio), _mode: PhantomData }
     }
+    /// Configures the pin to operate as a push-pull output pin with an initial state.
+    ///
+    /// The output state is written before the pin direction is changed, so the pin
+    /// never drives any other level.
+    pub fn into_push_pull_output_in_state(self, gpio: &mut PIN::Gpio, state: PinState) -> Gpio<PIN, Output<PushPull>> {
+        self.pin.set_state(state);
+        Gpio { pin: self.pin.configure_push_pull_output(gpio), _mode: PhantomData }
+    }
     /// Configures the pin to operate as an open-drain output pin.
     pub fn into_open_drain_output(self, gpio: &mut PIN::Gpio) -> Gpio<PIN, Output<OpenDrain>> {
         Gpio { pin: self.pin.configure_open_drain_output(gpio), _mode: PhantomData }
     }
+    /// Configures the pin to operate as an open-drain output pin with an initial state.
+    ///
+    /// The output state is written before the pin direction is changed, so the pin
+    /// never drives any other level.
+    pub fn into_open_drain_output_in_state(self, gpio: &mut PIN::Gpio, state: PinState) -> Gpio<PIN, Output<OpenDrain>> {
+        self.pin.set_state(state);
+        Gpio { pin: self.pin.configure_open_drain_output(gpio), _mode: PhantomData }
+    }
     /// Configures the pin to operate as a floating input pin.
     pub fn into_floating_input(self, gpio: &mut PIN::Gpio) -> Gpio<PIN, Input<Floating>> {
         Gpio { pin: self.pin.configure_floating_input(gpio), _mode: PhantomData }
@@ -108,6 +200,83 @@ impl<PIN: Alt1Pin, MODE> Gpio<PIN, MODE> {
     pub fn into_pull_down_input(self, gpio: &mut PIN::Gpio) -> Gpio<PIN, Input<PullDown>> {
         Gpio { pin: self.pin.configure_pull_down_input(gpio), _mode: PhantomData }
     }
+    /// Configures the pin to operate as a runtime switchable input and output pin.
+    ///
+    /// The pin starts as a floating input.
+    pub fn into_flex(self, gpio: &mut PIN::Gpio) -> Gpio<PIN, Flex> {
+        Gpio { pin: self.pin.configure_floating_input(gpio), _mode: PhantomData }
+    }
+}
+
+impl<PIN: Alt1Pin> Gpio<PIN, Flex> {
+    /// Switches the pin into input direction with the given pull resistor.
+    pub fn set_as_input(&mut self, pull: Pull) {
+        self.pin.set_pull(pull);
+        self.pin.set_direction_input();
+    }
+    /// Switches the pin into push-pull output direction.
+    ///
+    /// The pin drives the level last written by `OutputPin` functions.
+    pub fn set_as_output(&mut self) {
+        self.pin.set_open_drain(false);
+        self.pin.set_direction_output();
+    }
+    /// Switches the pin into open-drain output direction.
+    ///
+    /// The pin drives the level last written by `OutputPin` functions.
+    pub fn set_as_open_drain_output(&mut self) {
+        self.pin.set_open_drain(true);
+        self.pin.set_direction_output();
+    }
+    /// Returns `true` if the pin is currently in output direction.
+    pub fn is_output(&self) -> bool {
+        self.pin.is_direction_output()
+    }
+}
+
+impl<PIN: Alt1Pin> OutputPin for Gpio<PIN, Flex> {
+    type Error = Infallible;
+
+    fn try_set_low(&mut self) -> Result<(), Self::Error> {
+        self.pin.set_low();
+        Ok(())
+    }
+
+    fn try_set_high(&mut self) -> Result<(), Self::Error> {
+        self.pin.set_high();
+        Ok(())
+    }
+}
+
+impl<PIN: Alt1Pin> StatefulOutputPin for Gpio<PIN, Flex> {
+    fn try_is_set_high(&self) -> Result<bool, Infallible> {
+        Ok(self.pin.is_set_high())
+    }
+
+    fn try_is_set_low(&self) -> Result<bool, Infallible> {
+        Ok(!self.pin.is_set_high())
+    }
+}
+
+impl<PIN: Alt1Pin> ToggleableOutputPin for Gpio<PIN, Flex> {
+    type Error = Infallible;
+
+    fn try_toggle(&mut self) -> Result<(), Self::Error> {
+        self.pin.toggle();
+        Ok(())
+    }
+}
+
+impl<PIN: Alt1Pin> InputPin for Gpio<PIN, Flex> {
+    type Error = Infallible;
+
+    fn try_is_high(&self) -> Result<bool, Self::Error> {
+        Ok(self.pin.is_high())
+    }
+
+    fn try_is_low(&self) -> Result<bool, Self::Error> {
+        Ok(!self.pin.is_high())
+    }
 }
 
 impl<PIN: Alt1Pin, MODE> OutputPin for Gpio<PIN, Output<MODE>> {
@@ -147,33 +316,257 @@ impl<PIN: Alt1Pin, MODE> InputPin for Gpio<PIN, Input<MODE>> {
     type Error = Infallible;
 
     fn try_is_high(&self) -> Result<bool, Self::Error> {
-        Ok(self.pin.is_set_high())
+        Ok(self.pin.is_high())
     }
 
     fn try_is_low(&self) -> Result<bool, Self::Error> {
-        Ok(!self.pin.is_set_high())
+        Ok(!self.pin.is_high())
+    }
+}
+
+impl<PIN: Alt1Pin, MODE> Gpio<PIN, Input<MODE>> {
+    /// Waits until the pin is high; returns at once if it is already high.
+    pub async fn wait_for_high(&mut self) -> Result<(), Infallible> {
+        Wait::new(&self.pin, IRQC_LOGIC_ONE).await
+    }
+    /// Waits until the pin is low; returns at once if it is already low.
+    pub async fn wait_for_low(&mut self) -> Result<(), Infallible> {
+        Wait::new(&self.pin, IRQC_LOGIC_ZERO).await
+    }
+    /// Waits for a rising edge on the pin.
+    pub async fn wait_for_rising_edge(&mut self) -> Result<(), Infallible> {
+        Wait::new(&self.pin, IRQC_RISING_EDGE).await
     }
+    /// Waits for a falling edge on the pin.
+    pub async fn wait_for_falling_edge(&mut self) -> Result<(), Infallible> {
+        Wait::new(&self.pin, IRQC_FALLING_EDGE).await
+    }
+    /// Waits for either a rising or a falling edge on the pin.
+    pub async fn wait_for_any_edge(&mut self) -> Result<(), Infallible> {
+        Wait::new(&self.pin, IRQC_EITHER_EDGE).await
+    }
+}
+
+// PCR IRQC field values for interrupt on a pin event
+const IRQC_DISABLED: u8 = 0;
+const IRQC_LOGIC_ZERO: u8 = 8;
+const IRQC_RISING_EDGE: u8 = 9;
+const IRQC_FALLING_EDGE: u8 = 10;
+const IRQC_EITHER_EDGE: u8 = 11;
+const IRQC_LOGIC_ONE: u8 = 12;
+
+#[allow(clippy::declare_interior_mutable_const)]
+const NO_WAKER: Mutex<RefCell<Option<Waker>>> = Mutex::new(RefCell::new(None));
+#[allow(clippy::declare_interior_mutable_const)]
+const NO_WAKERS: [Mutex<RefCell<Option<Waker>>>; 32] = [NO_WAKER; 32];
+
+// Wakers of tasks waiting for pin events, indexed by port and pin number
+static WAKERS: [[Mutex<RefCell<Option<Waker>>>; 32]; 4] = [NO_WAKERS; 4];
+
+// The pin interrupt is armed on first poll; the interrupt handler disarms it
+// when the event occurs, and the next poll sees the disarmed pin.
+struct Wait<'a, PIN: Alt1Pin> {
+    pin: &'a PIN,
+    irqc: u8,
+    armed: bool,
+}
+
+impl<'a, PIN: Alt1Pin> Wait<'a, PIN> {
+    fn new(pin: &'a PIN, irqc: u8) -> Self {
+        Wait { pin, irqc, armed: false }
+    }
+}
+
+impl<PIN: Alt1Pin> Future for Wait<'_, PIN> {
+    type Output = Result<(), Infallible>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.get_mut();
+        let (port, index) = this.pin.interrupt_index();
+        riscv::interrupt::free(|cs| {
+            if this.armed && this.pin.interrupt_config() == IRQC_DISABLED {
+                return Poll::Ready(Ok(()));
+            }
+            WAKERS[port][index].borrow(cs).replace(Some(cx.waker().clone()));
+            if !this.armed {
+                this.pin.set_interrupt(this.irqc);
+                this.armed = true;
+            }
+            Poll::Pending
+        })
+    }
+}
+
+impl<PIN: Alt1Pin> Drop for Wait<'_, PIN> {
+    fn drop(&mut self) {
+        let (port, index) = self.pin.interrupt_index();
+        riscv::interrupt::free(|cs| {
+            if self.armed {
+                self.pin.set_interrupt(IRQC_DISABLED);
+            }
+            WAKERS[port][index].borrow(cs).replace(None);
+        })
+    }
+}
+
+fn wake_pins(port: usize, flags: u32) {
+    riscv::interrupt::free(|cs| {
+        for (index, waker) in WAKERS[port].iter().enumerate() {
+            if flags & (1 << index) != 0 {
+                if let Some(waker) = waker.borrow(cs).borrow_mut().take() {
+                    waker.wake();
+                }
+            }
+        }
+    })
+}
+
+/// Wakes tasks waiting for PORTA pin events; call from the PORTA interrupt handler.
+pub fn on_porta_interrupt() {
+    wake_pins(0, porta::disable_pending_interrupts())
+}
+
+/// Wakes tasks waiting for PORTB pin events; call from the PORTB interrupt handler.
+pub fn on_portb_interrupt() {
+    wake_pins(1, portb::disable_pending_interrupts())
+}
+
+/// Wakes tasks waiting for PORTC pin events; call from the PORTC interrupt handler.
+pub fn on_portc_interrupt() {
+    wake_pins(2, portc::disable_pending_interrupts())
+}
+
+/// Wakes tasks waiting for PORTD pin events; call from the PORTD interrupt handler.
+pub fn on_portd_interrupt() {
+    wake_pins(3, portd::disable_pending_interrupts())
 }
 
 impl<PIN: Alt1Pin> InputPin for Gpio<PIN, Output<OpenDrain>> {
     type Error = Infallible;
 
     fn try_is_high(&self) -> Result<bool, Self::Error> {
-        Ok(self.pin.is_set_high())
+        Ok(self.pin.is_high())
     }
 
     fn try_is_low(&self) -> Result<bool, Self::Error> {
-        Ok(!self.pin.is_set_high())
+        Ok(!self.pin.is_high())
+    }
+}
+
+/// Debounced input event
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum DebounceEvent {
+    /// Input became active and stayed active for the stable time
+    Pressed,
+    /// Input became inactive and stayed inactive for the stable time
+    Released,
+    /// Input has been active for the long press time
+    LongPressed,
+}
+
+/// Debouncer config
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct DebounceConfig {
+    /// Time in microseconds the input must keep a level before it is accepted
+    pub stable_time_us: u32,
+    /// Time in microseconds the input must stay active to report a long press
+    pub long_press_time_us: u32,
+    /// Input is active when the pin is low, like a button to ground with a pull-up
+    pub active_low: bool,
+}
+
+impl Default for DebounceConfig {
+    fn default() -> DebounceConfig {
+        DebounceConfig {
+            stable_time_us: 20_000,
+            long_press_time_us: 1_000_000,
+            active_low: true,
+        }
+    }
+}
+
+/// Software debouncer over any input pin, timed by TSTMR timestamps
+///
+/// For polled use, call `update` periodically. For interrupt-driven use, call
+/// `update` from the pin interrupt handler on every edge, and again when the
+/// time stamp returned by `next_deadline` is reached.
+pub struct Debouncer<PIN> {
+    pin: PIN,
+    config: DebounceConfig,
+    active: bool,
+    raw_active: bool,
+    raw_since: u64,
+    long_pressed: bool,
+}
+
+impl<PIN: InputPin> Debouncer<PIN> {
+    /// Wraps an input pin; the input starts as inactive
+    pub fn new(pin: PIN, config: DebounceConfig) -> Self {
+        Debouncer {
+            pin,
+            config,
+            active: false,
+            raw_active: false,
+            raw_since: crate::tstmr::timestamp(),
+            long_pressed: false,
+        }
+    }
+
+    /// Samples the pin and returns the event decided by this sample, if any
+    pub fn update(&mut self) -> Result<Option<DebounceEvent>, PIN::Error> {
+        let raw_active = self.pin.try_is_high()? != self.config.active_low;
+        let now = crate::tstmr::timestamp();
+        if raw_active != self.raw_active {
+            self.raw_active = raw_active;
+            self.raw_since = now;
+        }
+        let elapsed = now.wrapping_sub(self.raw_since);
+        if raw_active != self.active && elapsed >= self.config.stable_time_us as u64 {
+            self.active = raw_active;
+            self.long_pressed = false;
+            return Ok(Some(match raw_active {
+                true => DebounceEvent::Pressed,
+                false => DebounceEvent::Released,
+            }));
+        }
+        if self.active && !self.long_pressed && elapsed >= self.config.long_press_time_us as u64 {
+            self.long_pressed = true;
+            return Ok(Some(DebounceEvent::LongPressed));
+        }
+        Ok(None)
+    }
+
+    /// Returns the time stamp at which `update` may decide the next event
+    ///
+    /// Returns `None` if no event is pending until the input level changes.
+    pub fn next_deadline(&self) -> Option<u64> {
+        if self.raw_active != self.active {
+            Some(self.raw_since + self.config.stable_time_us as u64)
+        } else if self.active && !self.long_pressed {
+            Some(self.raw_since + self.config.long_press_time_us as u64)
+        } else {
+            None
+        }
+    }
+
+    /// Returns `true` if the debounced input is active
+    pub fn is_pressed(&self) -> bool {
+        self.active
+    }
+
+    /// Releases the input pin
+    pub fn release(self) -> PIN {
+        self.pin
     }
 }
 
 macro_rules! gpio_impl {
-    ($GPIOX: ident, $gpiox: ident, $gpioy: ident, $portx: ident, [
+    ($GPIOX: ident, $gpiox: ident, $gpioy: ident, $portx: ident, $port_index: expr, [
         $( $PTXi: ident: $i: expr, )+
     ]) => {
 mod $gpiox {
     use crate::port::$portx::*;
-    use super::{Gpio, Output, PushPull, OpenDrain, Input, Floating, PullUp, PullDown};
+    use super::{Gpio, Output, PushPull, OpenDrain, Input, Floating, PullUp, PullDown, Flex, Pull, PinState};
     use super::ALT1;
     use crate::pac;
     use core::marker::PhantomData;
@@ -190,6 +583,13 @@ mod $gpiox {
         });
     }
 
+    #[inline] fn modify_gpio_output_state($gpiox: &mut pac::$GPIOX, idx: usize, state: PinState) {
+        match state {
+            PinState::Low => $gpiox.pcor.write(|w| unsafe { w.ptco().bits(1 << idx) }),
+            PinState::High => $gpiox.psor.write(|w| unsafe { w.ptso().bits(1 << idx) }),
+        }
+    }
+
     const GPIO_PTR: *const pac::$gpioy::RegisterBlock = pac::$GPIOX::ptr();
 
 $(
@@ -200,12 +600,32 @@ $(
             let pin = self.into_af1_no_open_drain();
             Gpio { pin, _mode: PhantomData }
         }
+        /// Configures the pin to operate as a push-pull output pin with an initial state.
+        ///
+        /// The output state is written before the pin direction is changed, so the pin
+        /// never drives any other level.
+        pub fn into_push_pull_output_in_state(self, $gpiox: &mut pac::$GPIOX, state: PinState) -> Gpio<$PTXi<ALT1>, Output<PushPull>> {
+            modify_gpio_output_state($gpiox, $i, state);
+            modify_gpio_direction_out($gpiox, $i);
+            let pin = self.into_af1_no_open_drain();
+            Gpio { pin, _mode: PhantomData }
+        }
         /// Configures the pin to operate as an open-drain output pin.
         pub fn into_open_drain_output(self, $gpiox: &mut pac::$GPIOX) -> Gpio<$PTXi<ALT1>, Output<OpenDrain>> {
             modify_gpio_direction_out($gpiox, $i);
             let pin = self.into_af1_with_open_drain();
             Gpio { pin, _mode: PhantomData }
         }
+        /// Configures the pin to operate as an open-drain output pin with an initial state.
+        ///
+        /// The output state is written before the pin direction is changed, so the pin
+        /// never drives any other level.
+        pub fn into_open_drain_output_in_state(self, $gpiox: &mut pac::$GPIOX, state: PinState) -> Gpio<$PTXi<ALT1>, Output<OpenDrain>> {
+            modify_gpio_output_state($gpiox, $i, state);
+            modify_gpio_direction_out($gpiox, $i);
+            let pin = self.into_af1_with_open_drain();
+            Gpio { pin, _mode: PhantomData }
+        }
         /// Configures the pin to operate as a floating input pin.
         pub fn into_floating_input(self, $gpiox: &mut pac::$GPIOX) -> Gpio<$PTXi<ALT1>, Input<Floating>> {
             modify_gpio_direction_in($gpiox, $i);
@@ -224,6 +644,14 @@ $(
             let pin = self.into_af1_pull_down();
             Gpio { pin, _mode: PhantomData }
         }
+        /// Configures the pin to operate as a runtime switchable input and output pin.
+        ///
+        /// The pin starts as a floating input.
+        pub fn into_flex(self, $gpiox: &mut pac::$GPIOX) -> Gpio<$PTXi<ALT1>, Flex> {
+            modify_gpio_direction_in($gpiox, $i);
+            let pin = self.into_af1_no_pull();
+            Gpio { pin, _mode: PhantomData }
+        }
     }
 
     impl super::Alt1Pin for $PTXi<ALT1> {
@@ -263,6 +691,35 @@ $(
         fn is_high(&self) -> bool {
             unsafe { &*GPIO_PTR }.pdir.read().bits() & (1 << $i) != 0
         }
+        fn set_direction_output(&self) {
+            // PDDR is shared by all pins of this port; do the read-modify-write atomically
+            riscv::interrupt::free(|_| unsafe { &*GPIO_PTR }.pddr.modify(|r, w| unsafe {
+                w.pdd().bits(r.pdd().bits() | (1 << $i))
+            }));
+        }
+        fn set_direction_input(&self) {
+            riscv::interrupt::free(|_| unsafe { &*GPIO_PTR }.pddr.modify(|r, w| unsafe {
+                w.pdd().bits(r.pdd().bits() & !(1 << $i))
+            }));
+        }
+        fn is_direction_output(&self) -> bool {
+            unsafe { &*GPIO_PTR }.pddr.read().bits() & (1 << $i) != 0
+        }
+        fn set_pull(&self, pull: Pull) {
+            self.modify_pull(pull)
+        }
+        fn set_open_drain(&self, value: bool) {
+            self.modify_open_drain(value)
+        }
+        fn set_interrupt(&self, irqc: u8) {
+            self.modify_interrupt(irqc)
+        }
+        fn interrupt_config(&self) -> u8 {
+            $PTXi::interrupt_config(self)
+        }
+        fn interrupt_index(&self) -> (usize, usize) {
+            ($port_index, $i)
+        }
     }
 )+
 }
@@ -310,7 +767,7 @@ $(
     }
 }
 
-gpio_impl! { GPIOA, gpioa, gpioa, porta, [
+gpio_impl! { GPIOA, gpioa, gpioa, porta, 0, [
     PTA0: 0,
     PTA1: 1,
     PTA2: 2,
@@ -336,7 +793,7 @@ gpio_impl! { GPIOA, gpioa, gpioa, porta, [
     PTA31: 31,
 ] }
 
-gpio_impl! { GPIOB, gpiob, gpioa, portb, [
+gpio_impl! { GPIOB, gpiob, gpioa, portb, 1, [
     PTB0: 0,
     PTB1: 1,
     PTB2: 2,
@@ -368,7 +825,7 @@ gpio_impl! { GPIOB, gpiob, gpioa, portb, [
     PTB31: 31,
 ] }
 
-gpio_impl! { GPIOC, gpioc, gpioa, portc, [
+gpio_impl! { GPIOC, gpioc, gpioa, portc, 2, [
     PTC0: 0,
     PTC1: 1,
     PTC7: 7,
@@ -384,7 +841,7 @@ gpio_impl! { GPIOC, gpioc, gpioa, portc, [
     PTC30: 30,
 ] }
 
-gpio_impl! { GPIOD, gpiod, gpioa, portd, [
+gpio_impl! { GPIOD, gpiod, gpioa, portd, 3, [
     PTD0: 0,
     PTD1: 1,
     PTD2: 2,
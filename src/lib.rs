@@ -3,12 +3,20 @@
 //! This is an implementation of the [`embedded-hal`] traits for the RI5CY
 //! core of RV32M1 SoC.
 // #![allow(unused)]
-#![no_std]
+#![cfg_attr(not(test), no_std)]
 
 pub use rv32m1_ri5cy_pac as pac;
 pub mod pcc;
+pub mod dma;
 pub mod port;
 pub mod gpio;
+pub mod lin;
+#[cfg(feature = "log")]
+pub mod logger;
+pub mod onewire;
+// the host test harness links the panic handler of std
+#[cfg(all(feature = "panic-serial", not(test)))]
+pub mod panic_serial;
 pub mod scg;
 pub mod serial;
 pub mod timer;
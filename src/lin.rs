@@ -0,0 +1,231 @@
+//! Local Interconnect Network (LIN)
+//!
+//! This module runs LIN 2.x master and slave frames over a [`Serial`] port. The
+//! LPUART sends 13-bit break characters and detects breaks in received data, so
+//! the serial port must be configured with `Config::long_break` and
+//! `Config::break_detect` set, 8 data bits and no parity.
+//!
+//! LIN is a single wire bus, so every node receives its own transmission as well.
+//! Master and slave run the same state machine in [`Lin::poll`]:
+//!
+//! - the master sends a header with [`Lin::send_header`];
+//! - on `LinEvent::Header`, the publisher of the frame calls [`Lin::publish`] and
+//!   all subscribers call [`Lin::subscribe`]; other nodes do nothing;
+//! - on `LinEvent::Response`, received data is available from [`Lin::data`].
+//!
+//! Ref: LIN Specification Package Revision 2.2A
+
+use crate::serial::{self, Event, Instance, RxPins, Serial, TxPins};
+use embedded_hal::serial::{Read, Write};
+
+const SYNC: u8 = 0x55;
+
+/// Maximum number of data bytes in a frame
+pub const MAX_DATA_LEN: usize = 8;
+
+/// LIN error
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum Error {
+    /// Serial receive error
+    Serial(serial::Error),
+    /// The byte after a break is not the sync byte
+    Sync,
+    /// Parity bits of the protected identifier are invalid
+    Parity,
+    /// Checksum of the response is invalid
+    Checksum,
+    /// Frame identifier is not in 0..=63, or response length is not in 1..=8
+    InvalidFrame,
+}
+
+impl From<serial::Error> for Error {
+    fn from(error: serial::Error) -> Self {
+        Error::Serial(error)
+    }
+}
+
+/// Checksum model of frames
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum ChecksumModel {
+    /// Sum of data bytes, LIN 1.x
+    Classic,
+    /// Sum of protected identifier and data bytes, LIN 2.x
+    ///
+    /// Diagnostic frames 0x3C and 0x3D always use the classic checksum.
+    Enhanced,
+}
+
+/// Completed part of a frame
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum LinEvent {
+    /// Header with the frame identifier was received
+    Header(u8),
+    /// Response of the frame identifier was received with a valid checksum
+    Response(u8),
+}
+
+#[derive(Copy, Clone)]
+enum State {
+    Idle,
+    Sync,
+    Pid,
+    // header received, waiting for `publish` or `subscribe`
+    Header,
+    Response { len: usize, received: usize },
+}
+
+/// LIN master or slave node
+pub struct Lin<UART: Instance, PINS> {
+    serial: Serial<UART, PINS>,
+    model: ChecksumModel,
+    state: State,
+    id: u8,
+    data: [u8; MAX_DATA_LEN],
+    len: usize,
+}
+
+impl<UART: Instance, PINS: TxPins<UART> + RxPins<UART>> Lin<UART, PINS> {
+    /// Creates a LIN node on a serial port
+    pub fn new(mut serial: Serial<UART, PINS>, model: ChecksumModel) -> Self {
+        serial.clear(Event::LinBreak);
+        Lin { serial, model, state: State::Idle, id: 0, data: [0; MAX_DATA_LEN], len: 0 }
+    }
+
+    /// Releases the serial port
+    pub fn free(self) -> Serial<UART, PINS> {
+        self.serial
+    }
+
+    /// Sends a break, sync byte and protected identifier as master
+    ///
+    /// The header is received back and reported by `poll` like headers of other masters.
+    pub fn send_header(&mut self, id: u8) -> Result<(), Error> {
+        if id > 0x3F {
+            return Err(Error::InvalidFrame);
+        }
+        nb::block!(self.serial.try_write_break())?;
+        nb::block!(self.serial.try_write(SYNC))?;
+        nb::block!(self.serial.try_write(protected_id(id)))?;
+        Ok(())
+    }
+
+    /// Sends the response to the last header
+    ///
+    /// The response is received back and reported by `poll`, which checks the
+    /// transmission for bit errors.
+    pub fn publish(&mut self, data: &[u8]) -> Result<(), Error> {
+        self.subscribe(data.len())?;
+        for &byte in data {
+            nb::block!(self.serial.try_write(byte))?;
+        }
+        let checksum = checksum(self.checksum_model(), self.id, data);
+        nb::block!(self.serial.try_write(checksum))?;
+        Ok(())
+    }
+
+    /// Receives a response of `len` bytes to the last header
+    pub fn subscribe(&mut self, len: usize) -> Result<(), Error> {
+        if len == 0 || len > MAX_DATA_LEN {
+            return Err(Error::InvalidFrame);
+        }
+        self.state = State::Response { len, received: 0 };
+        Ok(())
+    }
+
+    /// Identifier of the last header
+    pub fn id(&self) -> u8 {
+        self.id
+    }
+
+    /// Data of the last valid response
+    pub fn data(&self) -> &[u8] {
+        &self.data[..self.len]
+    }
+
+    /// Processes received words, returns when a header or response is complete
+    ///
+    /// A break restarts the state machine at any time. After an error the node
+    /// waits for the next break.
+    pub fn poll(&mut self) -> nb::Result<LinEvent, Error> {
+        if self.serial.is_pending(Event::LinBreak) {
+            self.serial.clear(Event::LinBreak);
+            self.state = State::Sync;
+        }
+        loop {
+            let byte: u8 = match self.serial.try_read() {
+                Ok(byte) => byte,
+                Err(nb::Error::WouldBlock) => return Err(nb::Error::WouldBlock),
+                Err(nb::Error::Other(error)) => {
+                    self.state = State::Idle;
+                    return Err(nb::Error::Other(error.into()));
+                }
+            };
+            match self.state {
+                // discard bytes of frames this node does not take part in
+                State::Idle | State::Header => {}
+                State::Sync => {
+                    if byte != SYNC {
+                        self.state = State::Idle;
+                        return Err(nb::Error::Other(Error::Sync));
+                    }
+                    self.state = State::Pid;
+                }
+                State::Pid => {
+                    let id = byte & 0x3F;
+                    if protected_id(id) != byte {
+                        self.state = State::Idle;
+                        return Err(nb::Error::Other(Error::Parity));
+                    }
+                    self.id = id;
+                    self.state = State::Header;
+                    return Ok(LinEvent::Header(id));
+                }
+                State::Response { len, received } if received < len => {
+                    if received == 0 {
+                        self.len = 0;
+                    }
+                    self.data[received] = byte;
+                    self.state = State::Response { len, received: received + 1 };
+                }
+                State::Response { len, .. } => {
+                    self.state = State::Idle;
+                    if checksum(self.checksum_model(), self.id, &self.data[..len]) != byte {
+                        return Err(nb::Error::Other(Error::Checksum));
+                    }
+                    self.len = len;
+                    return Ok(LinEvent::Response(self.id));
+                }
+            }
+        }
+    }
+
+    fn checksum_model(&self) -> ChecksumModel {
+        match self.id {
+            0x3C | 0x3D => ChecksumModel::Classic,
+            _ => self.model,
+        }
+    }
+}
+
+/// Adds parity bits P0 and P1 to a 6-bit frame identifier
+pub fn protected_id(id: u8) -> u8 {
+    let bit = |n: u8| (id >> n) & 1;
+    let p0 = bit(0) ^ bit(1) ^ bit(2) ^ bit(4);
+    let p1 = !(bit(1) ^ bit(3) ^ bit(4) ^ bit(5)) & 1;
+    (id & 0x3F) | (p0 << 6) | (p1 << 7)
+}
+
+/// Computes the checksum of a response with the given model
+pub fn checksum(model: ChecksumModel, id: u8, data: &[u8]) -> u8 {
+    let initial = match model {
+        ChecksumModel::Classic => 0,
+        ChecksumModel::Enhanced => protected_id(id) as u16,
+    };
+    let sum = data.iter().fold(initial, |sum, &byte| {
+        // add with carry wrapped around
+        let sum = sum + byte as u16;
+        (sum & 0xFF) + (sum >> 8)
+    });
+    !(sum as u8)
+}
@@ -0,0 +1,72 @@
+//! Logger over a serial port
+//!
+//! This module is a [`log`] backend, enabled by the `log` feature. Records are
+//! printed through one LPUART transmitter registered by [`init`], which is used
+//! in a critical section, so records from interrupt handlers are never
+//! interleaved with others.
+//!
+//! ```ignore
+//! let (tx, _rx) = serial.split();
+//! logger::init(tx, log::LevelFilter::Info).unwrap();
+//! log::info!("clock is {} Hz", 48_000_000);
+//! ```
+
+use crate::serial::{self, Instance, Transmit, TxPins};
+use core::cell::Cell;
+use core::fmt::{self, Write};
+use log::{LevelFilter, Log, Metadata, Record, SetLoggerError};
+use riscv::interrupt::{self, Mutex};
+
+// writes a string to the registered transmitter
+type WriteFn = fn(&str);
+
+static WRITER: Mutex<Cell<Option<WriteFn>>> = Mutex::new(Cell::new(None));
+
+static LOGGER: SerialLogger = SerialLogger;
+
+struct SerialLogger;
+
+impl Log for SerialLogger {
+    fn enabled(&self, _metadata: &Metadata) -> bool {
+        // records are filtered by the maximum level only
+        true
+    }
+
+    fn log(&self, record: &Record) {
+        interrupt::free(|cs| {
+            if let Some(write) = WRITER.borrow(cs).get() {
+                let mut writer = Writer(write);
+                write!(writer, "[{}] {}: {}\r\n", record.level(), record.target(), record.args()).ok();
+            }
+        })
+    }
+
+    fn flush(&self) {}
+}
+
+struct Writer(WriteFn);
+
+impl Write for Writer {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        (self.0)(s);
+        Ok(())
+    }
+}
+
+/// Registers the transmitter as the global logger with a maximum level
+///
+/// The transmitter is kept enabled for the rest of the program. Only one logger
+/// can be registered.
+pub fn init<UART: Instance, PINS: TxPins<UART>>(tx: Transmit<UART, PINS>, level: LevelFilter) -> Result<(), SetLoggerError> {
+    interrupt::free(|cs| {
+        // note(unsafe): no other logger can be set in critical section
+        unsafe {
+            log::set_logger_racy(&LOGGER)?;
+            log::set_max_level_racy(level);
+        }
+        // note(forget): dropping the transmitter would disable it
+        core::mem::forget(tx);
+        WRITER.borrow(cs).set(Some(serial::write_str::<UART>));
+        Ok(())
+    })
+}
@@ -0,0 +1,336 @@
+//! 1-Wire bus master
+//!
+//! This module drives a 1-Wire bus by bit-banging a [`Flex`] GPIO pin, using the
+//! Time Stamp Timer ([`tstmr`](crate::tstmr)) for slot timing. The bus needs an
+//! external pull-up resistor (typically 4.7 kΩ); the internal pull-up is enabled
+//! as well whenever the master releases the bus.
+//!
+//! Ref: Maxim Application Note 126, 1-Wire Communication Through Software
+
+use crate::gpio::{Alt1Pin, Flex, Gpio, Pull};
+use crate::tstmr::delay_us;
+use embedded_hal::digital::{InputPin, OutputPin};
+
+// Standard speed slot timings in microseconds, AN126 table 2
+const A: u32 = 6;
+const B: u32 = 64;
+const C: u32 = 60;
+const D: u32 = 10;
+const E: u32 = 9;
+const F: u32 = 55;
+const H: u32 = 480;
+const I: u32 = 70;
+const J: u32 = 410;
+
+const READ_ROM: u8 = 0x33;
+const MATCH_ROM: u8 = 0x55;
+const SKIP_ROM: u8 = 0xCC;
+const SEARCH_ROM: u8 = 0xF0;
+
+/// 1-Wire error
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum Error {
+    /// No device answered the reset pulse with a presence pulse
+    NoPresence,
+    /// The bus stays low while it is released
+    BusShorted,
+    /// CRC check of received data failed
+    Crc,
+    /// The device has an unexpected family code
+    FamilyCode,
+}
+
+/// 64-bit ROM code of a 1-Wire device
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct Rom(pub [u8; 8]);
+
+impl Rom {
+    /// Returns the family code, the first byte of the ROM code
+    pub fn family_code(&self) -> u8 {
+        self.0[0]
+    }
+
+    /// Returns `true` if the last byte of the ROM code is a valid CRC of the others
+    pub fn is_crc_valid(&self) -> bool {
+        crc8(&self.0[..7]) == self.0[7]
+    }
+}
+
+/// State of a ROM search, see [`OneWire::search_next`]
+#[derive(Debug, Copy, Clone)]
+pub struct Search {
+    rom: [u8; 8],
+    last_discrepancy: u8,
+    last_device: bool,
+}
+
+impl Search {
+    /// Creates a new search from the first device on the bus
+    pub fn new() -> Search {
+        Search { rom: [0; 8], last_discrepancy: 0, last_device: false }
+    }
+}
+
+impl Default for Search {
+    fn default() -> Search {
+        Search::new()
+    }
+}
+
+/// 1-Wire bus master
+pub struct OneWire<PIN> {
+    pin: Gpio<PIN, Flex>,
+}
+
+impl<PIN: Alt1Pin> OneWire<PIN> {
+    /// Creates a bus master on the given pin, releasing the bus
+    pub fn new(mut pin: Gpio<PIN, Flex>) -> Self {
+        // the output latch stays low; the bus is driven low by switching direction only
+        pin.try_set_low().unwrap();
+        pin.set_as_input(Pull::Up);
+        OneWire { pin }
+    }
+
+    /// Releases the pin
+    pub fn release(self) -> Gpio<PIN, Flex> {
+        self.pin
+    }
+
+    /// Sends a reset pulse and waits for a presence pulse
+    pub fn reset(&mut self) -> Result<(), Error> {
+        if !self.sample() {
+            return Err(Error::BusShorted);
+        }
+        let presence = riscv::interrupt::free(|_| {
+            self.drive_low();
+            delay_us(H);
+            self.release_bus();
+            delay_us(I);
+            !self.sample()
+        });
+        delay_us(J);
+        if !presence {
+            return Err(Error::NoPresence);
+        }
+        if !self.sample() {
+            return Err(Error::BusShorted);
+        }
+        Ok(())
+    }
+
+    /// Writes a single bit time slot
+    pub fn write_bit(&mut self, bit: bool) {
+        riscv::interrupt::free(|_| {
+            self.drive_low();
+            if bit {
+                delay_us(A);
+                self.release_bus();
+                delay_us(B);
+            } else {
+                delay_us(C);
+                self.release_bus();
+                delay_us(D);
+            }
+        })
+    }
+
+    /// Reads a single bit time slot
+    pub fn read_bit(&mut self) -> bool {
+        let bit = riscv::interrupt::free(|_| {
+            self.drive_low();
+            delay_us(A);
+            self.release_bus();
+            delay_us(E);
+            self.sample()
+        });
+        delay_us(F);
+        bit
+    }
+
+    /// Writes a byte, LSB first
+    pub fn write_byte(&mut self, byte: u8) {
+        for i in 0..8 {
+            self.write_bit(byte & (1 << i) != 0);
+        }
+    }
+
+    /// Reads a byte, LSB first
+    pub fn read_byte(&mut self) -> u8 {
+        let mut byte = 0;
+        for i in 0..8 {
+            if self.read_bit() {
+                byte |= 1 << i;
+            }
+        }
+        byte
+    }
+
+    /// Writes all bytes in `bytes`
+    pub fn write_bytes(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.write_byte(byte);
+        }
+    }
+
+    /// Fills `buf` with bytes read from the bus
+    pub fn read_bytes(&mut self, buf: &mut [u8]) {
+        for byte in buf.iter_mut() {
+            *byte = self.read_byte();
+        }
+    }
+
+    /// Resets the bus and addresses one device, or all devices if `rom` is `None`
+    pub fn select(&mut self, rom: Option<&Rom>) -> Result<(), Error> {
+        self.reset()?;
+        match rom {
+            Some(rom) => {
+                self.write_byte(MATCH_ROM);
+                self.write_bytes(&rom.0);
+            }
+            None => self.write_byte(SKIP_ROM),
+        }
+        Ok(())
+    }
+
+    /// Reads the ROM code of the only device on the bus
+    pub fn read_rom(&mut self) -> Result<Rom, Error> {
+        self.reset()?;
+        self.write_byte(READ_ROM);
+        let mut rom = Rom([0; 8]);
+        self.read_bytes(&mut rom.0);
+        if !rom.is_crc_valid() {
+            return Err(Error::Crc);
+        }
+        Ok(rom)
+    }
+
+    /// Finds the next device on the bus
+    ///
+    /// Returns `Ok(None)` after the last device has been found.
+    pub fn search_next(&mut self, search: &mut Search) -> Result<Option<Rom>, Error> {
+        if search.last_device {
+            return Ok(None);
+        }
+        self.reset()?;
+        self.write_byte(SEARCH_ROM);
+        let mut last_zero = 0;
+        for id_bit_number in 1..=64u8 {
+            let byte = ((id_bit_number - 1) / 8) as usize;
+            let mask = 1 << ((id_bit_number - 1) % 8);
+            let id_bit = self.read_bit();
+            let cmp_id_bit = self.read_bit();
+            let direction = match (id_bit, cmp_id_bit) {
+                // no device answered this bit
+                (true, true) => return Ok(None),
+                (true, false) => true,
+                (false, true) => false,
+                // discrepancy: devices with both values are present
+                (false, false) => {
+                    let direction = if id_bit_number < search.last_discrepancy {
+                        search.rom[byte] & mask != 0
+                    } else {
+                        id_bit_number == search.last_discrepancy
+                    };
+                    if !direction {
+                        last_zero = id_bit_number;
+                    }
+                    direction
+                }
+            };
+            if direction {
+                search.rom[byte] |= mask;
+            } else {
+                search.rom[byte] &= !mask;
+            }
+            self.write_bit(direction);
+        }
+        search.last_discrepancy = last_zero;
+        search.last_device = last_zero == 0;
+        let rom = Rom(search.rom);
+        if !rom.is_crc_valid() {
+            return Err(Error::Crc);
+        }
+        Ok(Some(rom))
+    }
+
+    fn drive_low(&mut self) {
+        self.pin.set_as_output();
+    }
+
+    fn release_bus(&mut self) {
+        self.pin.set_as_input(Pull::Up);
+    }
+
+    fn sample(&self) -> bool {
+        self.pin.try_is_high().unwrap()
+    }
+}
+
+/// Computes the Maxim 1-Wire CRC-8 (polynomial x^8 + x^5 + x^4 + 1)
+pub fn crc8(data: &[u8]) -> u8 {
+    let mut crc = 0;
+    for &byte in data {
+        let mut byte = byte;
+        for _ in 0..8 {
+            let mix = (crc ^ byte) & 0x01;
+            crc >>= 1;
+            if mix != 0 {
+                crc ^= 0x8C;
+            }
+            byte >>= 1;
+        }
+    }
+    crc
+}
+
+/// DS18B20 digital thermometer
+pub struct Ds18b20 {
+    rom: Option<Rom>,
+}
+
+impl Ds18b20 {
+    /// Family code of DS18B20 devices
+    pub const FAMILY_CODE: u8 = 0x28;
+
+    /// Addresses the device with the given ROM code
+    pub fn new(rom: Rom) -> Result<Self, Error> {
+        if rom.family_code() != Self::FAMILY_CODE {
+            return Err(Error::FamilyCode);
+        }
+        Ok(Ds18b20 { rom: Some(rom) })
+    }
+
+    /// Addresses the only device on the bus without a ROM code
+    pub fn single() -> Self {
+        Ds18b20 { rom: None }
+    }
+
+    /// Starts a temperature conversion
+    ///
+    /// A 12-bit conversion takes up to 750 ms.
+    pub fn start_conversion<PIN: Alt1Pin>(&self, bus: &mut OneWire<PIN>) -> Result<(), Error> {
+        bus.select(self.rom.as_ref())?;
+        bus.write_byte(0x44); // CONVERT T
+        Ok(())
+    }
+
+    /// Returns `true` if the conversion started last has finished
+    ///
+    /// Must be called right after `start_conversion`, without other bus traffic in between.
+    pub fn is_conversion_done<PIN: Alt1Pin>(&self, bus: &mut OneWire<PIN>) -> bool {
+        bus.read_bit()
+    }
+
+    /// Reads the last converted temperature, in units of 1/16 °C
+    pub fn read_temperature<PIN: Alt1Pin>(&self, bus: &mut OneWire<PIN>) -> Result<i16, Error> {
+        bus.select(self.rom.as_ref())?;
+        bus.write_byte(0xBE); // READ SCRATCHPAD
+        let mut scratchpad = [0u8; 9];
+        bus.read_bytes(&mut scratchpad);
+        if crc8(&scratchpad[..8]) != scratchpad[8] {
+            return Err(Error::Crc);
+        }
+        Ok(i16::from_le_bytes([scratchpad[0], scratchpad[1]]))
+    }
+}
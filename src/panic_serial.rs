@@ -0,0 +1,121 @@
+//! Panic handler that reports over a serial port
+//!
+//! This module is enabled by the `panic-serial` feature and installs the
+//! `#[panic_handler]` of the program, so it replaces crates like `panic-halt`.
+//!
+//! On panic, the LPUART chosen by [`init`] is reset and reinitialised as a
+//! polling transmitter, whatever state its owner left it in. The panic location
+//! and message are printed, then the core halts or resets. Pins of the LPUART
+//! must be routed by the application before, and are not touched by the handler.
+//! Before `init` is called, panics halt without output.
+//!
+//! ```ignore
+//! panic_serial::init::<pac::LPUART0>(&clocks, Source::SysOsc, 115_200.Bd(), AfterPanic::Reset).unwrap();
+//! ```
+
+use crate::pac;
+use crate::scg::{Clocks, Source};
+use crate::serial::{self, BaudError, BaudPlan, Instance, DEFAULT_BAUD_TOLERANCE_PPM};
+use core::cell::Cell;
+use core::fmt::{self, Write};
+use core::panic::PanicInfo;
+use embedded_time::rate::Baud;
+use riscv::interrupt::{self, Mutex};
+
+/// Action after the panic is reported
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum AfterPanic {
+    /// Halt the core in a loop, e.g. to attach a debugger
+    Halt,
+    /// Reset the system through the watchdog
+    ///
+    /// If the watchdog configuration has been locked while disabled, the core halts instead.
+    Reset,
+}
+
+#[derive(Copy, Clone)]
+struct Target {
+    plan: BaudPlan,
+    source: Source,
+    reinit: fn(BaudPlan, Source),
+    write: fn(&str),
+    flush: fn(),
+    after: AfterPanic,
+}
+
+static TARGET: Mutex<Cell<Option<Target>>> = Mutex::new(Cell::new(None));
+
+/// Chooses the LPUART, baud rate and action of the panic handler
+///
+/// The baud rate is checked here, so the panic handler cannot fail on it.
+pub fn init<UART: Instance>(clocks: &Clocks, source: Source, baudrate: Baud, after: AfterPanic) -> Result<(), BaudError> {
+    let plan = serial::plan_baudrate(clocks.of_source(source), baudrate, DEFAULT_BAUD_TOLERANCE_PPM)?;
+    let target = Target {
+        plan,
+        source,
+        reinit: serial::reinit_transmitter::<UART>,
+        write: serial::write_str::<UART>,
+        flush: serial::flush_transmitter::<UART>,
+        after,
+    };
+    interrupt::free(|cs| TARGET.borrow(cs).set(Some(target)));
+    Ok(())
+}
+
+struct Writer(fn(&str));
+
+impl Write for Writer {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        (self.0)(s);
+        Ok(())
+    }
+}
+
+#[panic_handler]
+fn panic(info: &PanicInfo) -> ! {
+    // note(unsafe): the program never returns from here
+    unsafe { interrupt::disable() };
+    let target = interrupt::free(|cs| TARGET.borrow(cs).get());
+    let target = match target {
+        Some(target) => target,
+        None => halt(),
+    };
+    (target.reinit)(target.plan, target.source);
+    let mut writer = Writer(target.write);
+    match info.location() {
+        Some(location) => write!(writer, "\r\npanicked at {}:{}:{}", location.file(), location.line(), location.column()),
+        None => write!(writer, "\r\npanicked"),
+    }.ok();
+    write!(writer, ":\r\n{}\r\n", info.message()).ok();
+    (target.flush)();
+    match target.after {
+        AfterPanic::Halt => halt(),
+        AfterPanic::Reset => reset(),
+    }
+}
+
+fn halt() -> ! {
+    loop {
+        core::sync::atomic::compiler_fence(core::sync::atomic::Ordering::SeqCst);
+    }
+}
+
+const WDOG_UNLOCK: u32 = 0xD928_C520;
+
+fn reset() -> ! {
+    let wdog = unsafe { &*pac::WDOG0::ptr() };
+    if wdog.cs.read().en().bit_is_clear() {
+        // enable the watchdog with the shortest timeout, if it can be updated
+        wdog.cnt.write(|w| unsafe { w.bits(WDOG_UNLOCK) });
+        let unlocked = (0..1000).any(|_| wdog.cs.read().ulk().bit_is_set());
+        if !unlocked {
+            halt();
+        }
+        wdog.toval.write(|w| unsafe { w.bits(1) });
+        wdog.cs.write(|w| w.en().set_bit().clk().clk_1().cmd32en().set_bit().update().set_bit());
+        while wdog.cs.read().rcs().bit_is_clear() {}
+    }
+    // writing a value other than the refresh or unlock keys resets at once
+    wdog.cnt.write(|w| unsafe { w.bits(0) });
+    halt()
+}
@@ -1,6 +1,7 @@
 //! Peripheral Clock Controller (PCC)
 
 use crate::pac::{pcc0, PCC0};
+use crate::scg::Source;
 
 pub trait PccExt {
     fn constrain(self) -> Pcc;
@@ -47,8 +48,8 @@ $(
             if self.reg().read().inuse().is_inuse_1() {
                 return Err(EnableError::InUse)
             }
-            // enable port clock
-            self.reg().write(|w| w.cgc().set_bit());
+            // enable port clock, keeping the clock source
+            self.reg().modify(|_, w| w.cgc().set_bit());
             Ok(())
         }
         pub(crate) fn disable(&self) {
@@ -61,15 +62,51 @@ $(
 }
 
 pcc_impl! {
+    DMA0, dma0, PCC_DMA0, pcc_dma0, "Direct Memory Access";
+    DMAMUX0, dmamux0, PCC_DMAMUX0, pcc_dmamux0, "DMA Channel Multiplexer";
     LPUART0, lpuart0, PCC_LPUART0, pcc_lpuart0, "Low-Power UART";
-    // LPUART1, lpuart1, PCC_LPUART1, pcc_lpuart1, "Low-Power UART";
-    // LPUART2, lpuart2, PCC_LPUART2, pcc_lpuart2, "Low-Power UART";
+    LPUART1, lpuart1, PCC_LPUART1, pcc_lpuart1, "Low-Power UART";
+    LPUART2, lpuart2, PCC_LPUART2, pcc_lpuart2, "Low-Power UART";
     PORTA, porta, PCC_PORTA, pcc_porta, "Port";
     PORTB, portb, PCC_PORTB, pcc_portb, "Port";
     PORTC, portc, PCC_PORTC, pcc_portc, "Port";
     PORTD, portd, PCC_PORTD, pcc_portd, "Port";
 }
 
+macro_rules! pcc_source_impl {
+    ($($REGX: ident,)+) => {
+$(
+    impl $REGX {
+        /// Enables the peripheral clock with the functional clock from `source`
+        pub(crate) fn try_enable_with_source(&self, source: Source) -> core::result::Result<(), EnableError> {
+            // clock source can only be changed while the clock is disabled; writes are
+            // ignored if the peripheral is absent or in use by another core
+            self.disable();
+            self.reg().write(|w| w.pcs().bits(clock_option(source)));
+            self.try_enable()
+        }
+    }
+)+
+    };
+}
+
+// Peripherals with a selectable functional clock
+pcc_source_impl! {
+    LPUART0,
+    LPUART1,
+    LPUART2,
+}
+
+// PCS clock option of an asynchronous clock source, Ref: Reference Manual, Table 30-2
+fn clock_option(source: Source) -> u8 {
+    match source {
+        Source::SysOsc => 1,
+        Source::Sirc => 2,
+        Source::Hirc => 3,
+        Source::LpFll => 6,
+    }
+}
+
 /// Error that may occur when enabling the peripheral
 #[derive(Clone, Copy, Debug)]
 pub enum EnableError {
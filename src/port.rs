@@ -41,6 +41,18 @@ pub struct ALT6;
 /// Alternate function 7 (type state)
 pub struct ALT7;
 
+/// Pin with configurable pull and open drain in every function - DO NOT IMPLEMENT THIS TRAIT
+///
+/// # Safety
+///
+/// Implemented by this crate for port pins only.
+pub unsafe trait PinConfig {
+    #[doc(hidden)]
+    fn configure_pull(&self, pull: crate::gpio::Pull);
+    #[doc(hidden)]
+    fn configure_open_drain(&self, value: bool);
+}
+
 macro_rules! port_impl {
     ($PORTX: ident, $portx: ident, $PTXx: ident, [
         $($PTXi: ident:
@@ -51,7 +63,7 @@ macro_rules! port_impl {
     ]) => {
 /// Port
 pub mod $portx {
-    use super::{PortExt, ALT0, ALT1, ALT2, ALT3, ALT4, ALT5, ALT6, ALT7};
+    use super::{PortExt, PinConfig, ALT0, ALT1, ALT2, ALT3, ALT4, ALT5, ALT6, ALT7};
     use core::marker::PhantomData;
     use crate::{pac, pcc};
     use crate::pcc::EnableError;
@@ -75,6 +87,21 @@ pub mod $portx {
         }
     }
 
+    /// Disables the interrupt of every pin whose interrupt flag is set, clears
+    /// the flags and returns them.
+    pub(crate) fn disable_pending_interrupts() -> u32 {
+        let port = unsafe { &*PORT_PTR };
+        let flags = port.isfr.read().bits();
+        $(
+            if flags & (1 << $i) != 0 {
+                port.$pcri.modify(|_, w| w.irqc().irqc_0()); // Interrupt Configuration: disabled
+            }
+        )+
+        // note(unsafe): W1C; only clears flags read above
+        port.isfr.write(|w| unsafe { w.bits(flags) });
+        flags
+    }
+
     impl Parts {
         /// Free and release the port taken so it could be used by another core.
         ///
@@ -161,6 +188,37 @@ $(
             );
             $PTXi { _function: PhantomData }
         }
+        #[inline] pub(crate) fn modify_pull(&self, pull: crate::gpio::Pull) {
+            use crate::gpio::Pull;
+            unsafe { &*PORT_PTR }.$pcri.modify(|_, w| match pull {
+                Pull::None => w.pe().clear_bit(), // Pull Enable: 0
+                Pull::Up => w.pe().set_bit().ps().set_bit(), // Pull Enable: 1, Pull Select: 1 (pullup)
+                Pull::Down => w.pe().set_bit().ps().clear_bit(), // Pull Enable: 1, Pull Select: 0 (pulldown)
+            });
+        }
+        #[inline] pub(crate) fn modify_interrupt(&self, irqc: u8) {
+            unsafe { &*PORT_PTR }.$pcri.modify(|_, w| unsafe { w
+                .irqc().bits(irqc) // Interrupt Configuration
+                .isf().set_bit() // W1C; clean the flag
+            });
+        }
+        #[inline] pub(crate) fn interrupt_config(&self) -> u8 {
+            unsafe { &*PORT_PTR }.$pcri.read().irqc().bits()
+        }
+        #[inline] pub(crate) fn modify_open_drain(&self, value: bool) {
+            unsafe { &*PORT_PTR }.$pcri.modify(|_, w| 
+                w.ode().bit(value) // Open Drain Enable
+            );
+        }
+    }
+
+    unsafe impl<AF> PinConfig for $PTXi<AF> {
+        fn configure_pull(&self, pull: crate::gpio::Pull) {
+            self.modify_pull(pull)
+        }
+        fn configure_open_drain(&self, value: bool) {
+            self.modify_open_drain(value)
+        }
     }
 )+
 }
@@ -2,170 +2,1082 @@
 //! 
 //! This serial module is based on on-chip Low Power Universal Asynchronous Receiver/Transmitter (LPUART).
 use crate::{
-    pac, 
-    port::{ALT2, ALT3, porta::*, portb::*, portc::*/*, portd::**/},
+    pac::{self, lpuart0::RegisterBlock},
+    port::{PinConfig, ALT2, ALT3, ALT4, porta::*, portb::*, portc::*, portd::*},
+    gpio::Pull,
     scg::{Clocks, Source},
     pcc::{self, EnableError},
+    dma,
+    tstmr,
 };
-use embedded_time::rate::{Extensions, Rate, Fraction, Hertz, Baud};
-use core::{mem, marker::PhantomData};
+use embedded_time::rate::{Extensions, Hertz, Baud};
+use core::{mem, ptr, marker::PhantomData, sync::atomic::{self, Ordering}};
 
 /// Serial abstraction
 pub struct Serial<UART, PINS> {
-    uart: PhantomData<UART>,
+    uart: UART,
     pins: PINS,
+    source: Source,
+    baud_tolerance_ppm: u32,
 }
 
-impl<PINS: Pins<pac::LPUART0>> Serial<pac::LPUART0, PINS> {
-    pub fn lpuart0(
-        lpuart0: pac::LPUART0,
+impl<UART: Instance, PINS: Pins<UART>> Serial<UART, PINS> {
+    /// Configures an LPUART peripheral into a serial port
+    pub fn new(
+        uart: UART,
         pins: PINS,
         config: Config,
         clocks: Clocks,
         source: Source,
-        pcc_lpuart0: &mut pcc::LPUART0,
-    ) -> Result<Self, EnableError> {
+        pcc_lpuart: &mut UART::Clock,
+    ) -> Result<Self, ConfigError> {
+        // 0. check configuration before touching the peripheral
+        let parity_bit = config.parity != Parity::ParityNone;
+        let frame_bits = match config.data_bits {
+            DataBits::Seven => 7,
+            DataBits::Eight => 8,
+            DataBits::Nine => 9,
+            DataBits::Ten => 10,
+        } + parity_bit as u8;
+        if frame_bits > 10 {
+            return Err(ConfigError::WordLength);
+        }
+        // calculate best config from baudrate settings
+        let source_clock = clocks.of_source(source);
+        let BaudPlan { osr, sbr, .. } = plan_baudrate(
+            source_clock, config.baudrate, config.baud_tolerance_ppm)?;
+        if let Some(pulse) = config.irda {
+            check_irda_pulse(pulse.samples(), osr)?;
+        }
+        // both kinds of matching share the MATCH register
+        let character_match = config.character_match.iter().any(Option::is_some);
+        if character_match && config.address_match.iter().any(Option::is_some) {
+            return Err(ConfigError::Match);
+        }
         // 1. peripheral power on
         // enable peripheral clock
-        pcc_lpuart0.try_enable()?;
+        UART::enable_clock(pcc_lpuart, source)?;
+        pins.configure();
+        let lpuart = unsafe { &*UART::ptr() };
         // reset device
-        lpuart0.global.write(|w| w.rst().set_bit());
-        lpuart0.global.write(|w| w.rst().clear_bit());
+        lpuart.global.write(|w| w.rst().set_bit());
+        lpuart.global.write(|w| w.rst().clear_bit());
         // 2. set BAUD baudrate regitser value
-        // calculate best config from baudrate settings
-        let source_clock = clocks.of_source(source);
-        let (osr, sbr, _baud_diff) = calculate_osr_sbr_from_baudrate(
-            source_clock, config.baudrate);
-        let both_edge = osr >= 4 && osr <= 7;
+        let both_edge = (4..=7).contains(&osr);
         let stop_bits = match config.stopbits {
             StopBits::STOP1 => false,
             StopBits::STOP2 => true,
         };
         // note(unsafe): value is valid from function
-        lpuart0.baud.write(|w| unsafe { w
+        lpuart.baud.write(|w| unsafe { w
             .osr().bits(osr - 1) // set osr bits
             .sbr().bits(sbr) // set sbr bits
             .bothedge().bit(both_edge)
             .sbns().bit(stop_bits)
-            .m10().clear_bit() // disable word bit 10 by now
+            .m10().bit(frame_bits == 10)
         });
+        match character_match {
+            true => set_match(lpuart, config.character_match, MATCFG_IDLE_MATCH_WAKEUP),
+            false => set_match(lpuart, config.address_match, MATCFG_ADDRESS_MATCH_WAKEUP),
+        }
         // 3. set STAT status register
         let msbf = match config.order {
             Order::LsbFirst => false,
             Order::MsbFirst => true,
         };
-        lpuart0.stat.write(|w| w.msbf().bit(msbf));
-        // 4. set CTRL control register
+        lpuart.stat.write(|w| w
+            .msbf().bit(msbf)
+            .brk13().bit(config.long_break)
+            .lbkde().bit(config.break_detect)
+            .rxinv().bit(config.rx_invert)
+        );
+        // 4. set FIFO and WATER registers
+        // watermarks must be less than FIFO depth
+        let param = lpuart.param.read();
+        let tx_watermark = config.tx_watermark.min((1 << param.txfifo().bits()) - 1);
+        let rx_watermark = config.rx_watermark.min((1 << param.rxfifo().bits()) - 1);
+        // note(unsafe): watermark is limited to FIFO depth
+        lpuart.water.write(|w| unsafe { w
+            .txwater().bits(tx_watermark)
+            .rxwater().bits(rx_watermark)
+        });
+        lpuart.fifo.write(|w| w
+            .txfe().bit(config.fifo)
+            .rxfe().bit(config.fifo)
+            .rxiden().variant(config.rx_idle.into())
+        );
+        // 5. set MODIR modem register
+        // note(unsafe): watermark is limited to FIFO depth
+        let rts_watermark = config.rts_watermark.min((1 << param.rxfifo().bits()) - 1);
+        lpuart.modir.write(|w| unsafe { w
+            .rxrtse().bit(PINS::RTS)
+            .rtswater().bits(rts_watermark)
+            .txctse().bit(PINS::CTS)
+            .txctssrc().bit(config.cts_source == CtsSource::ReceiverMatch)
+            .txctsc().bit(config.cts_sampling == CtsSampling::TransmitterIdle)
+            .txrtse().bit(PINS::DRIVER_ENABLE)
+            .txrtspol().bit(config.driver_enable == DriverEnable::ActiveHigh)
+            .iren().bit(config.irda.is_some())
+            .tnp().bits(config.irda.map_or(0, |pulse| pulse.samples() - 1))
+        });
+        // 6. set CTRL control register
         let (parity_enable, parity_type) = match config.parity {
             Parity::ParityNone => (false, false),
             Parity::ParityEven => (true, false),
             Parity::ParityOdd => (true, true),
         };
-        let mode_bit = config.parity != Parity::ParityNone; // true -> 1 parity bit -> 9 bit word
-        lpuart0.ctrl.write(|w| w
-            .te().set_bit()
-            .re().set_bit()
+        // parity bit is the most significant bit of the word
+        lpuart.ctrl.write(|w| w
             .pe().bit(parity_enable)
             .pt().bit(parity_type)
-            .m().bit(mode_bit)
+            .m7().bit(frame_bits == 7)
+            .m().bit(frame_bits == 9)
+            .wake().bit(config.wakeup == Wakeup::AddressMark)
+            .dozeen().bit(config.stop_in_doze)
+            .loops().bit(PINS::SINGLE_WIRE)
+            .rsrc().bit(PINS::SINGLE_WIRE)
+            .ilt().bit(config.idle_start == IdleStart::AfterStopBit)
+            .idlecfg().variant(config.idle_length.into())
+            .txinv().bit(config.tx_invert)
         );
-        // 5. finished, return ownership
-        Ok(Serial { uart: PhantomData, pins })
+        // enable after polarity is set, so the TX pin idles at the right level
+        lpuart.ctrl.modify(|_, w| w.te().bit(PINS::TRANSMIT).re().bit(PINS::RECEIVE));
+        // 7. finished, return ownership
+        Ok(Serial { uart, pins, source, baud_tolerance_ppm: config.baud_tolerance_ppm })
     }
 
-    pub fn release(self, pcc_lpuart: &mut pcc::LPUART0) -> (pac::LPUART0, PINS) {
-        // note(unsafe): owned type
-        let lpuart = unsafe { mem::transmute::<_, pac::LPUART0>(()) };
+    pub fn release(self, pcc_lpuart: &mut UART::Clock) -> (UART, PINS) {
+        let lpuart = unsafe { &*UART::ptr() };
         // close the peripheral
         lpuart.ctrl.write(|w| w
             .te().clear_bit()
             .re().clear_bit()
         );
         // disable the clock
-        pcc_lpuart.disable();
+        UART::disable_clock(pcc_lpuart);
         // return ownership of peripherals
-        (lpuart, self.pins)
+        (self.uart, self.pins)
+    }
+
+    /// Splits the serial port into transmit and receive halves
+    ///
+    /// Each half owns the pins it uses; RTS and CTS pins of flow control go with
+    /// the receiver and the transmitter respectively.
+    pub fn split(self) -> (Transmit<UART, PINS>, Receive<UART, PINS>) {
+        let (tx, rx) = self.pins.split();
+        (
+            Transmit {
+                uart: PhantomData,
+                pins: tx,
+                source: self.source,
+                baud_tolerance_ppm: self.baud_tolerance_ppm,
+            },
+            Receive { uart: PhantomData, pins: rx },
+        )
+    }
+
+    /// Merges the halves back into a serial port, e.g. to reconfigure or release it
+    ///
+    /// Transmitter and receiver are kept in the state the halves left them.
+    pub fn merge(tx: Transmit<UART, PINS>, rx: Receive<UART, PINS>) -> Self {
+        // note(forget): dropping the halves would disable the peripheral
+        let tx = mem::ManuallyDrop::new(tx);
+        let rx = mem::ManuallyDrop::new(rx);
+        // note(unsafe): the halves are never dropped, so each pin is moved out once
+        let pins = unsafe { PINS::merge(ptr::read(&tx.pins), ptr::read(&rx.pins)) };
+        // note(unsafe): the halves owned the peripheral together
+        Serial {
+            uart: unsafe { UART::steal() },
+            pins,
+            source: tx.source,
+            baud_tolerance_ppm: tx.baud_tolerance_ppm,
+        }
+    }
+
+    /// Connects the transmitter output to the receiver input inside the LPUART
+    ///
+    /// Transmitted words are received back without leaving the chip, which allows
+    /// a self-test of the port. The TX pin is not driven in loopback. Disabling
+    /// loopback restores the mode given by the pins, e.g. a [`SingleWire`] port.
+    pub fn set_loopback(&mut self, enable: bool) {
+        let lpuart = unsafe { &*UART::ptr() };
+        lpuart.ctrl.modify(|_, w| w
+            .loops().bit(enable || PINS::SINGLE_WIRE)
+            .rsrc().bit(!enable && PINS::SINGLE_WIRE)
+        );
+    }
+}
+
+impl<UART: Instance, PINS> Serial<UART, PINS> {
+    /// Changes the baud rate
+    ///
+    /// The baud rate error must be within `Config::baud_tolerance_ppm` of the port.
+    ///
+    /// Words in the transmitter are sent before the change. Transmitter and receiver
+    /// are disabled while the BAUD register is written, then restored.
+    pub fn set_baudrate(&mut self, baudrate: Baud, clocks: &Clocks) -> Result<(), ConfigError> {
+        let lpuart = unsafe { &*UART::ptr() };
+        let BaudPlan { osr, sbr, .. } = plan_baudrate(
+            clocks.of_source(self.source), baudrate, self.baud_tolerance_ppm)?;
+        let modir = lpuart.modir.read();
+        if modir.iren().bit_is_set() {
+            check_irda_pulse(modir.tnp().bits() + 1, osr)?;
+        }
+        // BAUD must only be written when transmitter and receiver are disabled
+        reconfigure(lpuart, || {
+            // note(unsafe): value is valid from function
+            lpuart.baud.modify(|_, w| unsafe { w
+                .osr().bits(osr - 1)
+                .sbr().bits(sbr)
+                .bothedge().bit((4..=7).contains(&osr))
+            });
+        });
+        Ok(())
+    }
+
+    /// Changes the polarity of transmitted and received signals, see `Config::tx_invert`
+    ///
+    /// Words in the transmitter are sent before the change. Transmitter and receiver
+    /// are disabled while the polarity changes, so the TX pin moves straight to
+    /// the new idle level without sending a start bit.
+    pub fn set_inversion(&mut self, tx_invert: bool, rx_invert: bool) {
+        let lpuart = unsafe { &*UART::ptr() };
+        reconfigure(lpuart, || {
+            lpuart.ctrl.modify(|_, w| w.txinv().bit(tx_invert));
+            // note(unsafe): only RXINV changes, W1C flags are written with zero
+            lpuart.stat.modify(|r, w| unsafe { w.bits(r.bits() & !STAT_W1C_MASK) }.rxinv().bit(rx_invert));
+        });
+    }
+
+    /// Detects the baud rate from a sync character 0x55 and configures it
+    ///
+    /// Blocks until the sync character is received, or `timeout_us` microseconds
+    /// have passed. Interrupts are only disabled from the first falling edge of the
+    /// receive line until the character is timed. Edges are timed with
+    /// [`tstmr`](crate::tstmr), so the rate is measured to one microsecond in eight
+    /// bit times; the measured rate is rounded to a standard baud rate within 3%.
+    /// The sync character itself is not received.
+    ///
+    /// Returns `AutoBaudError::OutOfRange` if one microsecond in eight bit times
+    /// exceeds `Config::baud_tolerance_ppm`, e.g. above about 160 kBd with the
+    /// default tolerance, if the edges are not evenly spaced, or if no standard
+    /// baud rate is close to the measured one.
+    ///
+    /// `Config::rx_invert` must be `false`, and words must be sent LSB first.
+    pub fn detect_baudrate(&mut self, clocks: &Clocks, timeout_us: u32) -> Result<Baud, AutoBaudError> {
+        let lpuart = unsafe { &*UART::ptr() };
+        let re = lpuart.ctrl.read().re().bit();
+        lpuart.ctrl.modify(|_, w| w.re().clear_bit());
+        let start = tstmr::timestamp();
+        let timed_out = || tstmr::timestamp().wrapping_sub(start) > timeout_us as u64;
+        // 0x55 has falling edges at start bit, bit 1, bit 3, bit 5 and bit 7
+        let mut edges = [0u64; 5];
+        clear_flags(lpuart, STAT_RXEDGIF);
+        let result = loop {
+            // interrupts are served between polls until the start bit arrives
+            let timed = riscv::interrupt::free(|_| {
+                if lpuart.stat.read().rxedgif().bit_is_clear() {
+                    return None;
+                }
+                for edge in edges.iter_mut() {
+                    while lpuart.stat.read().rxedgif().bit_is_clear() {
+                        if timed_out() {
+                            return Some(Err(AutoBaudError::Timeout));
+                        }
+                    }
+                    *edge = tstmr::timestamp();
+                    clear_flags(lpuart, STAT_RXEDGIF);
+                }
+                Some(Ok(()))
+            });
+            match timed {
+                Some(result) => break result,
+                None if timed_out() => break Err(AutoBaudError::Timeout),
+                None => {}
+            }
+        };
+        let eight_bits_us = edges[4].wrapping_sub(edges[0]) as u32;
+        if result.is_ok() {
+            // skip the rest of the sync character: bit 7 and stop bit
+            tstmr::delay_us(eight_bits_us / 4 + 1);
+        }
+        lpuart.ctrl.modify(|_, w| w.re().bit(re));
+        result?;
+        // one timer tick in the measured time must be within the baud tolerance
+        if eight_bits_us == 0 || 1_000_000 / eight_bits_us > self.baud_tolerance_ppm {
+            return Err(AutoBaudError::OutOfRange);
+        }
+        // the first edge is stale if its poll was delayed past the next one
+        let uneven = edges.windows(2).any(|pair| {
+            let quarter_us = 4 * pair[1].wrapping_sub(pair[0]) as u32;
+            quarter_us.max(eight_bits_us) - quarter_us.min(eight_bits_us) > eight_bits_us / 8 + 4
+        });
+        if uneven {
+            return Err(AutoBaudError::OutOfRange);
+        }
+        let measured = 8 * tstmr::FREQUENCY_HZ / eight_bits_us;
+        let baudrate = STANDARD_BAUDRATES.iter().copied()
+            .find(|&standard| measured.max(standard) - measured.min(standard) <= standard * 3 / 100)
+            .ok_or(AutoBaudError::OutOfRange)?;
+        self.set_baudrate(Baud::new(baudrate), clocks)?;
+        Ok(Baud::new(baudrate))
+    }
+
+    /// Arms the receive edge interrupt to wake the system from a low-power mode
+    ///
+    /// Call this right before entering a stop mode, and [`resync`](Self::resync)
+    /// after waking up. The LPUART must not be stopped in Doze mode. If its clock
+    /// source keeps running in the stop mode, like the Slow IRC with stop enable,
+    /// the first character is received in full; otherwise it is lost. Waking from
+    /// LLS also needs the receive pin to be a LLWU wakeup source.
+    pub fn arm_wakeup(&mut self) {
+        let lpuart = unsafe { &*UART::ptr() };
+        clear_flags(lpuart, STAT_RXEDGIF);
+        set_interrupt(lpuart, Event::RxEdge, true);
+    }
+
+    /// Disarms the receive edge interrupt after waking up, returns `true` if an edge woke the LPUART
+    ///
+    /// A first character that was only partially received, because the clock was
+    /// still starting, is discarded along with its error flags; a character that
+    /// was received in full is kept for reading.
+    pub fn resync(&mut self) -> bool {
+        let lpuart = unsafe { &*UART::ptr() };
+        set_interrupt(lpuart, Event::RxEdge, false);
+        let stat = lpuart.stat.read();
+        let woken = stat.rxedgif().bit_is_set();
+        if stat.fe().bit_is_set() || stat.nf().bit_is_set() {
+            clear_flags(lpuart, STAT_FE | STAT_NF);
+            if stat.rdrf().bit_is_set() {
+                lpuart.data.read();
+            }
+        }
+        clear_flags(lpuart, STAT_RXEDGIF);
+        woken
+    }
+
+    /// Puts the receiver into standby until the wakeup condition in `Config::wakeup`
+    ///
+    /// Words received in standby are discarded. With `Wakeup::AddressMark` the
+    /// receiver wakes on a word with its most significant bit set, which must also
+    /// equal one of `Config::address_match` if any is given.
+    pub fn mute(&mut self) {
+        let lpuart = unsafe { &*UART::ptr() };
+        lpuart.ctrl.modify(|_, w| w.rwu().set_bit());
+    }
+
+    /// Returns `true` if the receiver is in standby waiting for wakeup
+    pub fn is_muted(&self) -> bool {
+        let lpuart = unsafe { &*UART::ptr() };
+        lpuart.ctrl.read().rwu().bit_is_set()
+    }
+
+    /// Changes the received addresses that wake up the receiver, see `Config::address_match`
+    ///
+    /// Replaces the characters of `set_character_match`.
+    pub fn set_address_match(&mut self, address_match: [Option<u16>; 2]) {
+        set_match(unsafe { &*UART::ptr() }, address_match, MATCFG_ADDRESS_MATCH_WAKEUP)
+    }
+
+    /// Changes the received characters that raise `Event::Match1` and `Event::Match2`,
+    /// see `Config::character_match`
+    ///
+    /// Replaces the addresses of `set_address_match`.
+    pub fn set_character_match(&mut self, character_match: [Option<u16>; 2]) {
+        set_match(unsafe { &*UART::ptr() }, character_match, MATCFG_IDLE_MATCH_WAKEUP)
+    }
+
+    /// Discards all words in the transmit FIFO
+    pub fn flush_tx_fifo(&mut self) {
+        flush_tx_fifo(unsafe { &*UART::ptr() })
+    }
+
+    /// Discards all words in the receive FIFO
+    pub fn flush_rx_fifo(&mut self) {
+        flush_rx_fifo(unsafe { &*UART::ptr() })
+    }
+
+    /// Queues a break character, in order with the written words
+    ///
+    /// The break is 13 bit times long if `Config::long_break` is set.
+    pub fn try_write_break(&mut self) -> nb::Result<(), Error> {
+        write_break(unsafe { &*UART::ptr() })
     }
 
-    pub fn split(self) -> (Transmit<pac::LPUART0, PINS>, Receive<pac::LPUART0, PINS>) {
-        (Transmit { uart: PhantomData, pins: PhantomData }, Receive { uart: PhantomData, pins: PhantomData })
+    /// Starts listening for an interrupt event
+    pub fn listen(&mut self, event: Event) {
+        set_interrupt(unsafe { &*UART::ptr() }, event, true)
     }
 
-    // pub fn merge(tx: Transmit<pac::LPUART0, PINS>, rx: Receive<pac::LPUART0, PINS>) -> Self {
-    //     Serial { uart: PhantomData, pins: tx.pins }
-    // }
+    /// Stops listening for an interrupt event
+    pub fn unlisten(&mut self, event: Event) {
+        set_interrupt(unsafe { &*UART::ptr() }, event, false)
+    }
+
+    /// Returns `true` if the flag of the event is set
+    pub fn is_pending(&self, event: Event) -> bool {
+        is_pending(unsafe { &*UART::ptr() }, event)
+    }
+
+    /// Clears the flag of the event
+    ///
+    /// Data register flags of `RxNotEmpty`, `TxEmpty` and `TxComplete` events are
+    /// only cleared by reading or writing words, so clearing them has no effect.
+    pub fn clear(&mut self, event: Event) {
+        clear_event(unsafe { &*UART::ptr() }, event)
+    }
+}
+
+impl<UART: Instance, PINS: RxPins<UART>> Serial<UART, PINS> {
+    /// Receives one burst of words, see [`read_until_idle`](Receive::read_until_idle)
+    pub fn read_until_idle(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+        read_until_idle(unsafe { &*UART::ptr() }, buf)
+    }
+}
+
+/// Half-duplex RS-485 serial port
+///
+/// The RTS pin drives the driver enable input of the transceiver. It is asserted
+/// one bit time before the start bit of the first word, and deasserted one bit
+/// time after the stop bit of the last word in the transmit buffer.
+pub type Rs485<UART, TX, RX, DE> = Serial<UART, (TX, RX, DE)>;
+
+impl<UART: Instance, TX, RX, DE> Rs485<UART, TX, RX, DE> {
+    /// Waits until transmission is complete before turning the bus around
+    ///
+    /// Returns `Ok` once the last stop bit is sent; the driver enable is released
+    /// one bit time later. Words echoed by the transceiver while transmitting are
+    /// discarded when `discard_echo` is `true`.
+    pub fn try_turnaround(&mut self, discard_echo: bool) -> nb::Result<(), Error> {
+        let lpuart = unsafe { &*UART::ptr() };
+        flush(lpuart)?;
+        if discard_echo {
+            while let Ok(_) | Err(nb::Error::Other(_)) = read(lpuart) {}
+        }
+        Ok(())
+    }
+}
+
+/// Single-wire half-duplex serial port
+///
+/// Transmitter and receiver share the TX pin, which is configured as open drain
+/// with its internal pull-up. The pin is an input after configuration; call
+/// [`start_transmit`](Serial::start_transmit) before writing and
+/// [`try_start_receive`](Serial::try_start_receive) when the transfer is done.
+pub type HalfDuplex<UART, TX> = Serial<UART, SingleWire<TX>>;
+
+impl<UART: Instance, TX> HalfDuplex<UART, TX> {
+    /// Drives the TX pin with the transmitter
+    pub fn start_transmit(&mut self) {
+        let lpuart = unsafe { &*UART::ptr() };
+        lpuart.ctrl.modify(|_, w| w.txdir().set_bit());
+    }
+
+    /// Waits until transmission is complete, then releases the TX pin to receive
+    ///
+    /// The receiver listens to the pin while transmitting as well; words echoed
+    /// during the transfer are discarded when `discard_echo` is `true`.
+    pub fn try_start_receive(&mut self, discard_echo: bool) -> nb::Result<(), Error> {
+        let lpuart = unsafe { &*UART::ptr() };
+        flush(lpuart)?;
+        lpuart.ctrl.modify(|_, w| w.txdir().clear_bit());
+        if discard_echo {
+            while let Ok(_) | Err(nb::Error::Other(_)) = read(lpuart) {}
+        }
+        Ok(())
+    }
+}
+
+macro_rules! serial_impl {
+    ($($LPUARTX: ident: ($lpuartx: ident, $rx_request: expr, $tx_request: expr),)+) => {
+$(
+    impl<PINS: Pins<pac::$LPUARTX>> Serial<pac::$LPUARTX, PINS> {
+        #[doc = "Configures"]
+        #[doc = stringify!($LPUARTX)]
+        #[doc = "into a serial port"]
+        pub fn $lpuartx(
+            $lpuartx: pac::$LPUARTX,
+            pins: PINS,
+            config: Config,
+            clocks: Clocks,
+            source: Source,
+            pcc_lpuart: &mut pcc::$LPUARTX,
+        ) -> Result<Self, ConfigError> {
+            Self::new($lpuartx, pins, config, clocks, source, pcc_lpuart)
+        }
+    }
+
+    unsafe impl Instance for pac::$LPUARTX {
+        type Clock = pcc::$LPUARTX;
+        const DMA_RX_REQUEST: u8 = $rx_request;
+        const DMA_TX_REQUEST: u8 = $tx_request;
+
+        fn ptr() -> *const RegisterBlock {
+            // note: all LPUART instances share the same register layout
+            pac::$LPUARTX::ptr() as *const RegisterBlock
+        }
+
+        fn enable_clock(clock: &mut pcc::$LPUARTX, source: Source) -> Result<(), EnableError> {
+            clock.try_enable_with_source(source)
+        }
+
+        fn disable_clock(clock: &mut pcc::$LPUARTX) {
+            clock.disable();
+        }
+
+        unsafe fn steal_clock() -> pcc::$LPUARTX {
+            mem::transmute::<(), pcc::$LPUARTX>(())
+        }
+
+        unsafe fn steal() -> Self {
+            mem::transmute::<(), pac::$LPUARTX>(())
+        }
+    }
+    // note: impls for each instance, as a generic `UART` could overlap with `(TX, RX)`
+    unsafe impl<TX: TxPin<pac::$LPUARTX>> Pins<pac::$LPUARTX> for (TX, NoRx) {
+        const RECEIVE: bool = false;
+        type Tx = TX;
+        type Rx = NoRx;
+
+        fn split(self) -> (TX, NoRx) {
+            self
+        }
+
+        fn merge(tx: TX, rx: NoRx) -> Self {
+            (tx, rx)
+        }
+    }
+
+    unsafe impl<RX: RxPin<pac::$LPUARTX>> Pins<pac::$LPUARTX> for (NoTx, RX) {
+        const TRANSMIT: bool = false;
+        type Tx = NoTx;
+        type Rx = RX;
+
+        fn split(self) -> (NoTx, RX) {
+            self
+        }
+
+        fn merge(tx: NoTx, rx: RX) -> Self {
+            (tx, rx)
+        }
+    }
+
+    unsafe impl<TX: TxPin<pac::$LPUARTX>> TxPins<pac::$LPUARTX> for (TX, NoRx) {}
+    unsafe impl<RX: RxPin<pac::$LPUARTX>> RxPins<pac::$LPUARTX> for (NoTx, RX) {}
+)+
+    };
+}
+
+// DMAMUX request sources of receive and transmit, Ref: Reference Manual, Table 16-1
+serial_impl! {
+    LPUART0: (lpuart0, 15, 16),
+    LPUART1: (lpuart1, 17, 18),
+    LPUART2: (lpuart2, 19, 20),
+}
+
+/// LPUART peripheral instance - DO NOT IMPLEMENT THIS TRAIT
+///
+/// LPUART3 is attached to the other core's peripheral bus, so it is not
+/// accessible from the RI5CY core and has no instance here.
+///
+/// # Safety
+///
+/// Implemented by this crate for LPUART peripherals only.
+pub unsafe trait Instance {
+    /// Peripheral clock controller of this LPUART
+    type Clock;
+    #[doc(hidden)]
+    const DMA_RX_REQUEST: u8;
+    #[doc(hidden)]
+    const DMA_TX_REQUEST: u8;
+    #[doc(hidden)]
+    fn ptr() -> *const RegisterBlock;
+    #[doc(hidden)]
+    fn enable_clock(clock: &mut Self::Clock, source: Source) -> Result<(), EnableError>;
+    #[doc(hidden)]
+    fn disable_clock(clock: &mut Self::Clock);
+    #[doc(hidden)]
+    unsafe fn steal_clock() -> Self::Clock;
+    #[doc(hidden)]
+    unsafe fn steal() -> Self;
 }
 
 /// Transmit half of serial port
-pub struct Transmit<UART, PINS> {
+pub struct Transmit<UART: Instance, PINS: Pins<UART>> {
     uart: PhantomData<UART>,
-    pins: PhantomData<PINS>,
+    pins: PINS::Tx,
+    source: Source,
+    baud_tolerance_ppm: u32,
 }
 
-impl<UART, PINS> Drop for Transmit<UART, PINS> {
+impl<UART: Instance, PINS: TxPins<UART>> Transmit<UART, PINS> {
+    /// Discards all words in the transmit FIFO
+    pub fn flush_tx_fifo(&mut self) {
+        flush_tx_fifo(unsafe { &*UART::ptr() })
+    }
+}
+
+impl<UART: Instance, PINS: Pins<UART>> Drop for Transmit<UART, PINS> {
     fn drop(&mut self) {
-        let lpuart = unsafe { mem::transmute::<_, pac::LPUART0>(()) };
-        lpuart.ctrl.write(|w| w
-            .te().clear_bit()
-        );
-        let pcc_lpuart = unsafe { mem::transmute::<_, pcc::LPUART0>(()) };
+        let lpuart = unsafe { &*UART::ptr() };
+        // keep the receiver and configuration for the other half
+        lpuart.ctrl.modify(|_, w| w.te().clear_bit());
         if lpuart.ctrl.read().re().bit_is_clear() {
-            pcc_lpuart.disable();
+            // note(unsafe): this half owns the peripheral once the other half is gone
+            UART::disable_clock(&mut unsafe { UART::steal_clock() });
         }
     }
 }
 
 /// Receive half of serial port
-pub struct Receive<UART, PINS> {
+pub struct Receive<UART: Instance, PINS: Pins<UART>> {
     uart: PhantomData<UART>,
-    pins: PhantomData<PINS>,
+    pins: PINS::Rx,
+}
+
+impl<UART: Instance, PINS: RxPins<UART>> Receive<UART, PINS> {
+    /// Discards all words in the receive FIFO
+    pub fn flush_rx_fifo(&mut self) {
+        flush_rx_fifo(unsafe { &*UART::ptr() })
+    }
+
+    /// Receives one burst of words, returning the number of words in `buf`
+    ///
+    /// Blocks until the first word arrives, then returns when the line has been
+    /// idle for `Config::idle_length` characters and all received words are read.
+    /// A burst longer than `buf` is returned in parts; the call returns as soon
+    /// as `buf` is full, and the next call continues with the rest.
+    ///
+    /// In an `Event::Idle` interrupt handler the burst is already complete, so
+    /// the call returns without waiting. With the receive FIFO enabled, words can
+    /// be left in the FIFO until the line goes idle.
+    pub fn read_until_idle(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+        read_until_idle(unsafe { &*UART::ptr() }, buf)
+    }
 }
 
-impl<UART, PINS> Drop for Receive<UART, PINS> {
+impl<UART: Instance, PINS: Pins<UART>> Drop for Receive<UART, PINS> {
     fn drop(&mut self) {
-        let lpuart = unsafe { mem::transmute::<_, pac::LPUART0>(()) };
-        lpuart.ctrl.write(|w| w
-            .re().clear_bit()
-        );
-        let pcc_lpuart = unsafe { mem::transmute::<_, pcc::LPUART0>(()) };
+        let lpuart = unsafe { &*UART::ptr() };
+        // keep the transmitter and configuration for the other half
+        lpuart.ctrl.modify(|_, w| w.re().clear_bit());
         if lpuart.ctrl.read().te().bit_is_clear() {
-            pcc_lpuart.disable();
+            // note(unsafe): this half owns the peripheral once the other half is gone
+            UART::disable_clock(&mut unsafe { UART::steal_clock() });
+        }
+    }
+}
+
+impl<UART: Instance, PINS: TxPins<UART>> Transmit<UART, PINS> {
+    /// Transmits a static buffer through a DMA channel
+    ///
+    /// The transmit half and the channel are returned by [`TxDma::wait`] once all
+    /// words are handed over to the LPUART.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `buffer` is empty or longer than [`dma::MAX_TRANSFER_LEN`].
+    pub fn write_all_dma<CH: dma::Channel>(self, buffer: &'static [u8], channel: CH) -> TxDma<UART, PINS, CH> {
+        let lpuart = unsafe { &*UART::ptr() };
+        dma::configure(CH::NUMBER, UART::DMA_TX_REQUEST, dma::Transfer {
+            source: buffer.as_ptr() as u32,
+            source_offset: 1,
+            destination: &lpuart.data as *const _ as u32,
+            destination_offset: 0,
+            len: buffer.len(),
+            circular: false,
+        });
+        // buffer must be visible to the DMA before the first request
+        atomic::compiler_fence(Ordering::Release);
+        dma::start(CH::NUMBER);
+        lpuart.baud.modify(|_, w| w.tdmae().set_bit());
+        TxDma { tx: self, buffer, channel }
+    }
+}
+
+/// Transmit transfer through DMA
+pub struct TxDma<UART: Instance, PINS: Pins<UART>, CH> {
+    tx: Transmit<UART, PINS>,
+    buffer: &'static [u8],
+    channel: CH,
+}
+
+impl<UART: Instance, PINS: Pins<UART>, CH: dma::Channel> TxDma<UART, PINS, CH> {
+    /// Returns `true` if all words are handed over to the LPUART
+    ///
+    /// The last words may still be in the transmitter; flush the transmit half to
+    /// wait for them.
+    pub fn is_done(&self) -> bool {
+        dma::is_done(CH::NUMBER)
+    }
+
+    /// Number of words handed over to the LPUART
+    pub fn position(&self) -> usize {
+        match self.is_done() {
+            true => self.buffer.len(),
+            false => self.buffer.len() - dma::remaining(CH::NUMBER),
+        }
+    }
+
+    /// Blocks until the transfer is done, returns the transmit half, buffer and channel
+    pub fn wait(self) -> (Transmit<UART, PINS>, &'static [u8], CH) {
+        while !self.is_done() {}
+        self.stop()
+    }
+
+    /// Aborts the transfer, returns the transmit half, buffer and channel
+    pub fn stop(self) -> (Transmit<UART, PINS>, &'static [u8], CH) {
+        let lpuart = unsafe { &*UART::ptr() };
+        lpuart.baud.modify(|_, w| w.tdmae().clear_bit());
+        dma::stop(CH::NUMBER);
+        atomic::compiler_fence(Ordering::Acquire);
+        (self.tx, self.buffer, self.channel)
+    }
+}
+
+impl<UART: Instance, PINS: RxPins<UART>> Receive<UART, PINS> {
+    /// Receives continuously into a static circular buffer through a DMA channel
+    ///
+    /// Once the end of `buffer` is reached, the DMA wraps around to its start.
+    /// Received words must be read out by [`RxDma::read`] before they are
+    /// overwritten.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `buffer` is empty or longer than [`dma::MAX_TRANSFER_LEN`].
+    pub fn read_circular_dma<CH: dma::Channel>(self, buffer: &'static mut [u8], channel: CH) -> RxDma<UART, PINS, CH> {
+        let lpuart = unsafe { &*UART::ptr() };
+        dma::configure(CH::NUMBER, UART::DMA_RX_REQUEST, dma::Transfer {
+            source: &lpuart.data as *const _ as u32,
+            source_offset: 0,
+            destination: buffer.as_mut_ptr() as u32,
+            destination_offset: 1,
+            len: buffer.len(),
+            circular: true,
+        });
+        dma::start(CH::NUMBER);
+        lpuart.baud.modify(|_, w| w.rdmae().set_bit());
+        RxDma { rx: self, buffer, channel, read_position: 0 }
+    }
+}
+
+/// Circular receive transfer through DMA
+pub struct RxDma<UART: Instance, PINS: Pins<UART>, CH> {
+    rx: Receive<UART, PINS>,
+    buffer: &'static mut [u8],
+    channel: CH,
+    read_position: usize,
+}
+
+impl<UART: Instance, PINS: Pins<UART>, CH: dma::Channel> RxDma<UART, PINS, CH> {
+    /// Index in the buffer where the DMA writes the next received word
+    pub fn write_position(&self) -> usize {
+        // the counter reloads to the buffer length when the major loop completes
+        let position = self.buffer.len() - dma::remaining(CH::NUMBER);
+        position % self.buffer.len()
+    }
+
+    /// Index in the buffer of the next word returned by `read`
+    pub fn read_position(&self) -> usize {
+        self.read_position
+    }
+
+    /// Number of received words not read yet
+    ///
+    /// Words overwritten by a buffer overrun are not detected.
+    pub fn available(&self) -> usize {
+        let len = self.buffer.len();
+        (self.write_position() + len - self.read_position) % len
+    }
+
+    /// Moves received words into `buf`, returns the number of words read
+    pub fn read(&mut self, buf: &mut [u8]) -> usize {
+        let count = self.available().min(buf.len());
+        atomic::compiler_fence(Ordering::Acquire);
+        for word in buf[..count].iter_mut() {
+            // note(unsafe): the DMA writes to this buffer behind the compiler
+            *word = unsafe { ptr::read_volatile(&self.buffer[self.read_position]) };
+            self.read_position = (self.read_position + 1) % self.buffer.len();
+        }
+        count
+    }
+
+    /// Returns `true` if the flag of the event is set
+    ///
+    /// The match flags of `Event::Match1` and `Event::Match2` stay set after the
+    /// DMA has moved the matching word, so they mark the end of a received line
+    /// or packet; see `Config::character_match`.
+    pub fn is_pending(&self, event: Event) -> bool {
+        is_pending(unsafe { &*UART::ptr() }, event)
+    }
+
+    /// Clears the flag of the event
+    pub fn clear(&mut self, event: Event) {
+        clear_event(unsafe { &*UART::ptr() }, event)
+    }
+
+    /// Stops receiving, returns the receive half, buffer and channel
+    pub fn stop(self) -> (Receive<UART, PINS>, &'static mut [u8], CH) {
+        let lpuart = unsafe { &*UART::ptr() };
+        lpuart.baud.modify(|_, w| w.rdmae().clear_bit());
+        dma::stop(CH::NUMBER);
+        atomic::compiler_fence(Ordering::Acquire);
+        (self.rx, self.buffer, self.channel)
+    }
+}
+
+/// Interrupt driven serial port with transmit and receive ring buffers
+///
+/// Words are moved between the buffers and the LPUART by `on_interrupt`, which
+/// should be called from the LPUARTx interrupt handler. As both the handler and
+/// the application access this type, it is usually stored in a
+/// `Mutex<RefCell<Option<BufferedSerial<..>>>>` and used in critical sections.
+pub struct BufferedSerial<UART: Instance, PINS> {
+    serial: Serial<UART, PINS>,
+    tx: RingBuffer,
+    rx: RingBuffer,
+    error: Option<Error>,
+}
+
+impl<UART: Instance, PINS> BufferedSerial<UART, PINS> {
+    /// Wraps a serial port with transmit and receive buffers
+    pub fn new(mut serial: Serial<UART, PINS>, tx_buf: &'static mut [u8], rx_buf: &'static mut [u8]) -> Self {
+        for &event in &[Event::RxNotEmpty, Event::Overrun, Event::Noise, Event::Framing, Event::Parity] {
+            serial.listen(event);
+        }
+        BufferedSerial { serial, tx: RingBuffer::new(tx_buf), rx: RingBuffer::new(rx_buf), error: None }
+    }
+
+    /// Queues as many words of `data` as fit into the transmit buffer, returns the number queued
+    pub fn write(&mut self, data: &[u8]) -> usize {
+        let count = data.iter().take_while(|&&word| self.tx.push(word)).count();
+        if count > 0 {
+            self.serial.listen(Event::TxEmpty);
+        }
+        count
+    }
+
+    /// Moves received words into `buf`, returns the number of words read
+    pub fn read(&mut self, buf: &mut [u8]) -> usize {
+        let mut count = 0;
+        for word in buf.iter_mut() {
+            match self.rx.pop() {
+                Some(value) => *word = value,
+                None => break,
+            }
+            count += 1;
+        }
+        count
+    }
+
+    /// Number of words waiting in the transmit buffer
+    pub fn tx_len(&self) -> usize {
+        self.tx.len
+    }
+
+    /// Number of words waiting in the receive buffer
+    pub fn rx_len(&self) -> usize {
+        self.rx.len
+    }
+
+    /// Takes the first receive error since the last call
+    ///
+    /// Words that do not fit into a full receive buffer are reported as `Overrun`.
+    pub fn take_error(&mut self) -> Option<Error> {
+        self.error.take()
+    }
+
+    /// Services the LPUART; call this from the LPUARTx interrupt handler
+    pub fn on_interrupt(&mut self) {
+        let uart = unsafe { &*UART::ptr() };
+        loop {
+            match read(uart) {
+                Ok(word) => if !self.rx.push(word as u8) {
+                    self.error.get_or_insert(Error::Overrun);
+                },
+                Err(nb::Error::Other(error)) => {
+                    self.error.get_or_insert(error);
+                }
+                Err(nb::Error::WouldBlock) => break,
+            }
+        }
+        while self.tx.len > 0 && tx_ready(uart) {
+            if let Some(word) = self.tx.pop() {
+                write(uart, word as u16).ok();
+            }
+        }
+        if self.tx.len == 0 {
+            self.serial.unlisten(Event::TxEmpty);
+        }
+    }
+
+    /// Releases the serial port and buffers
+    pub fn free(mut self) -> (Serial<UART, PINS>, &'static mut [u8], &'static mut [u8]) {
+        for &event in &[Event::RxNotEmpty, Event::TxEmpty, Event::Overrun, Event::Noise, Event::Framing, Event::Parity] {
+            self.serial.unlisten(event);
+        }
+        (self.serial, self.tx.buf, self.rx.buf)
+    }
+}
+
+impl<UART: Instance, PINS: TxPins<UART>> embedded_hal::serial::Write<u8> for BufferedSerial<UART, PINS> {
+    /// Write error
+    type Error = Error;
+
+    /// Queues a single word into the transmit buffer
+    fn try_write(&mut self, word: u8) -> nb::Result<(), Self::Error> {
+        match self.write(&[word]) {
+            0 => Err(nb::Error::WouldBlock),
+            _ => Ok(()),
+        }
+    }
+
+    /// Ensures that the transmit buffer is empty and the last word is sent
+    fn try_flush(&mut self) -> nb::Result<(), Self::Error> {
+        if self.tx.len > 0 {
+            return Err(nb::Error::WouldBlock);
+        }
+        flush(unsafe { &*UART::ptr() })
+    }
+}
+
+impl<UART: Instance, PINS: RxPins<UART>> embedded_hal::serial::Read<u8> for BufferedSerial<UART, PINS> {
+    /// Read error
+    type Error = Error;
+
+    /// Reads a single word from the receive buffer, or reports a pending receive error
+    fn try_read(&mut self) -> nb::Result<u8, Self::Error> {
+        if let Some(error) = self.error.take() {
+            return Err(nb::Error::Other(error));
         }
+        self.rx.pop().ok_or(nb::Error::WouldBlock)
     }
 }
 
-const ONE: Fraction = Fraction::new(1, 1);
+struct RingBuffer {
+    buf: &'static mut [u8],
+    head: usize,
+    len: usize,
+}
+
+impl RingBuffer {
+    fn new(buf: &'static mut [u8]) -> Self {
+        RingBuffer { buf, head: 0, len: 0 }
+    }
+
+    fn push(&mut self, word: u8) -> bool {
+        if self.len == self.buf.len() {
+            return false;
+        }
+        let tail = (self.head + self.len) % self.buf.len();
+        self.buf[tail] = word;
+        self.len += 1;
+        true
+    }
 
-// OSR in [4, 32], SBR in [1, 8191]. Baud = Clock / ((OSR + 1) * SBR)
-fn calculate_osr_sbr_from_baudrate(source_clock: Hertz, target_baud: Baud) -> (u8, u16, Baud) {
-    let source_clock_hz = source_clock.to_generic::<u32>(ONE)
-        .expect("convert source clock to hertz");
-    let source_clock_hz = *source_clock_hz.integer();
-    let target_baud_bps = target_baud.to_generic::<u32>(ONE)
-        .expect("convert target baudrate to bps");
-    let target_baud_bps = *target_baud_bps.integer();
-    let mut baud_diff_bps = target_baud_bps;
-    let (mut osr, mut sbr): (u32, u32) = (0, 0);
-    for osr_tmp in 4..=32 {
-        let mut sbr_tmp: u32 = source_clock_hz / target_baud_bps * osr_tmp;
-        if sbr_tmp == 0 {
-            sbr_tmp = 1;
+    fn pop(&mut self) -> Option<u8> {
+        if self.len == 0 {
+            return None;
         }
-        let calc_baud_bps = source_clock_hz / (osr_tmp * sbr_tmp);
-        let mut tmp_diff_bps = calc_baud_bps - target_baud_bps;
-        if tmp_diff_bps > target_baud_bps - (source_clock_hz / (osr_tmp * (sbr_tmp + 1))) {
-            tmp_diff_bps = target_baud_bps - (source_clock_hz / (osr_tmp * (sbr_tmp + 1)));
-            sbr_tmp += 1;
+        let word = self.buf[self.head];
+        self.head = (self.head + 1) % self.buf.len();
+        self.len -= 1;
+        Some(word)
+    }
+}
+
+// Baud rates that automatic detection rounds to
+const STANDARD_BAUDRATES: [u32; 12] = [
+    1_200, 2_400, 4_800, 9_600, 19_200, 38_400, 57_600, 115_200, 230_400, 460_800, 921_600, 1_000_000,
+];
+
+// IrDA pulses are at most 3/16 of a bit time
+fn check_irda_pulse(samples: u8, osr: u8) -> Result<(), ConfigError> {
+    if 16 * samples as u32 > 3 * osr as u32 {
+        return Err(ConfigError::IrdaPulse);
+    }
+    Ok(())
+}
+
+/// Oversampling ratio and baud rate divisor that achieve a baud rate
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct BaudPlan {
+    /// Oversampling ratio, in 4..=32
+    pub osr: u8,
+    /// Baud rate modulo divisor, in 1..=8191
+    pub sbr: u16,
+    /// Achieved baud rate, rounded to nearest
+    pub baudrate: Baud,
+    /// Error of the achieved baud rate relative to the target, in parts per million
+    pub error_ppm: i32,
+}
+
+/// Baud rate planning error
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum BaudError {
+    /// Target baud rate or source clock is zero
+    Zero,
+    /// Target baud rate is too low for the source clock, even with the largest divisor
+    OutOfRange,
+    /// Error of the closest baud rate is beyond the tolerance
+    Tolerance {
+        /// Error of the closest baud rate, in parts per million
+        error_ppm: i32,
+    },
+}
+
+/// Baud rate error tolerated by `Serial`, in parts per million
+///
+/// Receivers sample the middle of each bit, so with 10 bits per word both sides
+/// together may drift up to about 5%; 2% is left for each side.
+pub const DEFAULT_BAUD_TOLERANCE_PPM: u32 = 20_000;
+
+/// Finds the OSR and SBR with the smallest error for a baud rate
+///
+/// Baud = source clock / (OSR * SBR), with OSR in 4..=32 and SBR in 1..=8191.
+/// Among divisors with equal error, the highest oversampling ratio is chosen.
+/// Plans with an error beyond `tolerance_ppm` are rejected; rates below the
+/// largest divisor fail with `BaudError::OutOfRange`.
+pub fn plan_baudrate(source_clock: Hertz, baudrate: Baud, tolerance_ppm: u32) -> Result<BaudPlan, BaudError> {
+    let clock = source_clock.0 as u128;
+    let target = baudrate.0 as u128;
+    if clock == 0 || target == 0 {
+        return Err(BaudError::Zero);
+    }
+    // (osr, sbr, divisor, |clock - target * divisor|)
+    let mut best: Option<(u8, u16, u128, u128)> = None;
+    for osr in 4..=32u8 {
+        let exact = clock / (osr as u128 * target);
+        // SBR beyond its range is clamped, so the closest rate at the limit is tried
+        let low = exact.clamp(1, 8191);
+        let high = (exact + 1).clamp(1, 8191);
+        for sbr in low..=high {
+            let divisor = osr as u128 * sbr;
+            let diff = (clock as i128 - (target * divisor) as i128).unsigned_abs();
+            // relative error is diff / (target * divisor); compare without division
+            let better = match best {
+                None => true,
+                Some((_, _, best_divisor, best_diff)) => diff * best_divisor <= best_diff * divisor,
+            };
+            if better {
+                best = Some((osr, sbr as u16, divisor, diff));
+            }
         }
-        if tmp_diff_bps < baud_diff_bps {
-            baud_diff_bps = tmp_diff_bps;
-            osr = osr_tmp;
-            sbr = sbr_tmp;
+    }
+    // note(unwrap): every OSR has at least one SBR candidate
+    let (osr, sbr, divisor, _) = best.unwrap();
+    let error_ppm = ((clock * 1_000_000) as i128 - (target * divisor * 1_000_000) as i128)
+        / (target * divisor) as i128;
+    if error_ppm.unsigned_abs() > tolerance_ppm as u128 || error_ppm > i32::MAX as i128 {
+        if clock > target * 32 * 8191 {
+            return Err(BaudError::OutOfRange);
         }
+        return Err(BaudError::Tolerance { error_ppm: error_ppm as i32 });
     }
-    assert!(osr >= 4 && osr <= 32);
-    assert!(sbr >= 1 && sbr <= 8191);
-    (osr as u8, sbr as u16, Baud::new(baud_diff_bps))
+    let error_ppm = error_ppm as i32;
+    let achieved = (clock + divisor / 2) / divisor;
+    Ok(BaudPlan { osr, sbr, baudrate: Baud::new(achieved as u32), error_ppm })
 }
 
 
@@ -183,6 +1095,82 @@ pub enum Error {
     Parity,
 }
 
+/// Serial configuration error
+#[derive(Debug, Copy, Clone)]
+#[non_exhaustive]
+pub enum ConfigError {
+    /// Peripheral clock could not be enabled
+    Clock(EnableError),
+    /// Baud rate cannot be achieved from the source clock
+    Baud(BaudError),
+    /// Data bits and parity bit exceed the 10-bit word of LPUART
+    WordLength,
+    /// IrDA pulse is longer than 3/16 of a bit time with the chosen oversampling ratio
+    IrdaPulse,
+    /// Address match and character match are both given
+    Match,
+}
+
+impl From<EnableError> for ConfigError {
+    fn from(error: EnableError) -> Self {
+        ConfigError::Clock(error)
+    }
+}
+
+impl From<BaudError> for ConfigError {
+    fn from(error: BaudError) -> Self {
+        ConfigError::Baud(error)
+    }
+}
+
+/// Automatic baud rate detection error
+#[derive(Debug, Copy, Clone)]
+#[non_exhaustive]
+pub enum AutoBaudError {
+    /// No sync character received before timeout
+    Timeout,
+    /// Baud rate is too fast to measure, or not close to a standard baud rate
+    OutOfRange,
+    /// Measured baud rate is invalid for the current configuration
+    Config(ConfigError),
+}
+
+impl From<ConfigError> for AutoBaudError {
+    fn from(error: ConfigError) -> Self {
+        AutoBaudError::Config(error)
+    }
+}
+
+/// Serial interrupt events
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum Event {
+    /// Receive data register full, or receive FIFO above watermark
+    RxNotEmpty,
+    /// Transmit data register empty, or transmit FIFO at or below watermark
+    TxEmpty,
+    /// Transmission complete
+    TxComplete,
+    /// Receiver detected an idle line of `Config::idle_length` characters after a word
+    Idle,
+    /// Receiver overrun
+    Overrun,
+    /// Noise detected in received word
+    Noise,
+    /// Framing error in received word
+    Framing,
+    /// Parity error in received word
+    Parity,
+    /// LIN break detected, if `Config::break_detect` is set
+    LinBreak,
+    /// Active edge on the receive pin, even while the receiver is stopped
+    RxEdge,
+    /// The next word to be read equals the first match value
+    Match1,
+    /// The next word to be read equals the second match value
+    Match2,
+}
+
 /// Parity check configuration
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
 pub enum Parity {
@@ -191,6 +1179,19 @@ pub enum Parity {
     ParityOdd,
 }
 
+/// Number of data bits in a word, not including the parity bit
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum DataBits {
+    /// 7 data bits
+    Seven,
+    /// 8 data bits
+    Eight,
+    /// 9 data bits, use `Write<u16>` and `Read<u16>`
+    Nine,
+    /// 10 data bits without parity, use `Write<u16>` and `Read<u16>`
+    Ten,
+}
+
 /// Stop bit configuration
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
 pub enum StopBits {
@@ -209,34 +1210,290 @@ pub enum Order {
     MsbFirst,
 }
 
+/// Receiver idle time before a partially filled receive FIFO asserts RDRF
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum RxIdle {
+    /// RDRF is asserted only when the receive watermark is exceeded
+    Disabled,
+    /// Idle for 1 character
+    Idle1,
+    /// Idle for 2 characters
+    Idle2,
+    /// Idle for 4 characters
+    Idle4,
+    /// Idle for 8 characters
+    Idle8,
+    /// Idle for 16 characters
+    Idle16,
+    /// Idle for 32 characters
+    Idle32,
+    /// Idle for 64 characters
+    Idle64,
+}
+
+impl From<RxIdle> for pac::lpuart0::fifo::RXIDEN_A {
+    fn from(value: RxIdle) -> Self {
+        use pac::lpuart0::fifo::RXIDEN_A::*;
+        match value {
+            RxIdle::Disabled => RXIDEN_0,
+            RxIdle::Idle1 => RXIDEN_1,
+            RxIdle::Idle2 => RXIDEN_2,
+            RxIdle::Idle4 => RXIDEN_3,
+            RxIdle::Idle8 => RXIDEN_4,
+            RxIdle::Idle16 => RXIDEN_5,
+            RxIdle::Idle32 => RXIDEN_6,
+            RxIdle::Idle64 => RXIDEN_7,
+        }
+    }
+}
+
+/// Number of idle characters that set the idle flag, see `Event::Idle`
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum IdleLength {
+    /// Idle for 1 character
+    Idle1,
+    /// Idle for 2 characters
+    Idle2,
+    /// Idle for 4 characters
+    Idle4,
+    /// Idle for 8 characters
+    Idle8,
+    /// Idle for 16 characters
+    Idle16,
+    /// Idle for 32 characters
+    Idle32,
+    /// Idle for 64 characters
+    Idle64,
+    /// Idle for 128 characters
+    Idle128,
+}
+
+impl From<IdleLength> for pac::lpuart0::ctrl::IDLECFG_A {
+    fn from(value: IdleLength) -> Self {
+        use pac::lpuart0::ctrl::IDLECFG_A::*;
+        match value {
+            IdleLength::Idle1 => IDLECFG_0,
+            IdleLength::Idle2 => IDLECFG_1,
+            IdleLength::Idle4 => IDLECFG_2,
+            IdleLength::Idle8 => IDLECFG_3,
+            IdleLength::Idle16 => IDLECFG_4,
+            IdleLength::Idle32 => IDLECFG_5,
+            IdleLength::Idle64 => IDLECFG_6,
+            IdleLength::Idle128 => IDLECFG_7,
+        }
+    }
+}
+
+/// Start of idle character counting
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum IdleStart {
+    /// Count from the start bit; stop bits and trailing ones of a word count as idle
+    AfterStartBit,
+    /// Count from the stop bit of a word
+    AfterStopBit,
+}
+
+/// Condition that wakes the receiver from standby, see [`Serial::mute`]
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Wakeup {
+    /// An idle line wakes the receiver
+    IdleLine,
+    /// A word with its most significant bit set wakes the receiver
+    AddressMark,
+}
+
+/// Width of IrDA transmit pulses, in oversampling clocks of a bit time
+///
+/// A bit time lasts OSR oversampling clocks, where OSR is chosen from the baud rate
+/// and source clock; standard IrDA pulses are 3/16 of a bit time.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum IrdaPulse {
+    /// 1/OSR of a bit time
+    Osr1,
+    /// 2/OSR of a bit time
+    Osr2,
+    /// 3/OSR of a bit time
+    Osr3,
+    /// 4/OSR of a bit time
+    Osr4,
+}
+
+impl IrdaPulse {
+    fn samples(self) -> u8 {
+        match self {
+            IrdaPulse::Osr1 => 1,
+            IrdaPulse::Osr2 => 2,
+            IrdaPulse::Osr3 => 3,
+            IrdaPulse::Osr4 => 4,
+        }
+    }
+}
+
+/// Polarity of the RS-485 driver enable signal on the RTS pin
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum DriverEnable {
+    /// Driver is enabled when RTS is high
+    ActiveHigh,
+    /// Driver is enabled when RTS is low
+    ActiveLow,
+}
+
+/// Input of the transmitter clear-to-send
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum CtsSource {
+    /// CTS pin
+    Pin,
+    /// Inverted receiver match result
+    ReceiverMatch,
+}
+
+/// Time when the transmitter samples clear-to-send
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum CtsSampling {
+    /// Sampled at the start of each character
+    StartOfCharacter,
+    /// Sampled when the transmitter is idle
+    TransmitterIdle,
+}
+
 /// Serial config
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
 pub struct Config {
     pub baudrate: Baud,
+    /// Maximum error of the achieved baud rate, in parts per million
+    pub baud_tolerance_ppm: u32,
+    pub data_bits: DataBits,
     pub parity: Parity,
     pub stopbits: StopBits,
     pub order: Order,
+    /// Enable transmit and receive FIFOs
+    pub fifo: bool,
+    /// TDRE is set when the number of words in transmit FIFO is equal or less than this value
+    pub tx_watermark: u8,
+    /// RDRF is set when the number of words in receive FIFO is greater than this value
+    pub rx_watermark: u8,
+    /// Idle time before RDRF is set for a receive FIFO below watermark
+    pub rx_idle: RxIdle,
+    /// Idle time that sets the idle flag after a received word
+    pub idle_length: IdleLength,
+    /// Start of idle character counting
+    pub idle_start: IdleStart,
+    /// RTS is deasserted when the receive FIFO has room for no more than this many words
+    pub rts_watermark: u8,
+    /// Input of the transmitter clear-to-send, if a CTS pin is used
+    pub cts_source: CtsSource,
+    /// Time when the transmitter samples clear-to-send, if a CTS pin is used
+    pub cts_sampling: CtsSampling,
+    /// Polarity of the driver enable signal, if an RS-485 pin set is used
+    pub driver_enable: DriverEnable,
+    /// Transmit breaks of 13 bit times instead of 10
+    pub long_break: bool,
+    /// Detect LIN breaks of 11 bit times as `Event::LinBreak` instead of receiving them
+    pub break_detect: bool,
+    /// Enable IrDA encoding and decoding with the given transmit pulse width
+    pub irda: Option<IrdaPulse>,
+    /// Stop the LPUART in Doze mode (CPU in wait or stop); keep it `false` to wake up on RX activity
+    pub stop_in_doze: bool,
+    /// Receiver wakeup method from standby
+    pub wakeup: Wakeup,
+    /// Address marks that are received, others are discarded along with the following
+    /// words; most useful with `Wakeup::AddressMark`
+    pub address_match: [Option<u16>; 2],
+    /// Received characters that raise `Event::Match1` and `Event::Match2`, e.g. `b'\n'`
+    ///
+    /// Matching words are received like any other word, and the match flags also
+    /// work with the receive FIFO and DMA. With `Wakeup::IdleLine`, a muted
+    /// receiver wakes up on a matching word after an idle line. Cannot be used
+    /// together with `address_match`.
+    pub character_match: [Option<u16>; 2],
+    /// Invert the transmitted signal, idling low instead of high
+    pub tx_invert: bool,
+    /// Invert the received signal, idling low instead of high
+    pub rx_invert: bool,
 }
 
 impl Default for Config {
     fn default() -> Config {
         Config {
             baudrate: 115_200_u32.Bd(),
+            baud_tolerance_ppm: DEFAULT_BAUD_TOLERANCE_PPM,
+            data_bits: DataBits::Eight,
             parity: Parity::ParityNone,
             stopbits: StopBits::STOP1,
             order: Order::LsbFirst,
+            fifo: false,
+            tx_watermark: 0,
+            rx_watermark: 0,
+            rx_idle: RxIdle::Disabled,
+            idle_length: IdleLength::Idle1,
+            idle_start: IdleStart::AfterStartBit,
+            rts_watermark: 0,
+            cts_source: CtsSource::Pin,
+            cts_sampling: CtsSampling::StartOfCharacter,
+            driver_enable: DriverEnable::ActiveHigh,
+            long_break: false,
+            break_detect: false,
+            irda: None,
+            stop_in_doze: false,
+            wakeup: Wakeup::IdleLine,
+            address_match: [None, None],
+            character_match: [None, None],
+            tx_invert: false,
+            rx_invert: false,
         }
     }
 }
 
 /// Serial transmit pins - DO NOT IMPLEMENT THIS TRAIT
+///
+/// # Safety
+///
+/// Implemented by this crate for pins routed to the LPUART only.
 pub unsafe trait TxPin<UART> {}
 /// Serial receive pins - DO NOT IMPLEMENT THIS TRAIT
+///
+/// # Safety
+///
+/// Implemented by this crate for pins routed to the LPUART only.
 pub unsafe trait RxPin<UART> {}
 /// Serial rts pins - DO NOT IMPLEMENT THIS TRAIT
-pub unsafe trait RtsPin<UART> {}
+///
+/// # Safety
+///
+/// Implemented by this crate for pins routed to the LPUART only.
+pub unsafe trait RtsPin<UART> {
+    #[doc(hidden)]
+    const ENABLED: bool = true;
+}
 /// Serial cts pins - DO NOT IMPLEMENT THIS TRAIT
-pub unsafe trait CtsPin<UART> {}
+///
+/// # Safety
+///
+/// Implemented by this crate for pins routed to the LPUART only.
+pub unsafe trait CtsPin<UART> {
+    #[doc(hidden)]
+    const ENABLED: bool = true;
+}
+
+/// TX pin of a single-wire half-duplex port, see [`HalfDuplex`]
+pub struct SingleWire<TX>(pub TX);
+
+/// Placeholder for an unused TX pin of a receive-only port, e.g. `(NoTx, rx)`
+pub struct NoTx;
+/// Placeholder for an unused RX pin of a transmit-only port, e.g. `(tx, NoRx)`
+pub struct NoRx;
+
+/// Placeholder for an unused RTS pin, e.g. `(tx, rx, NoRts, cts)`
+pub struct NoRts;
+/// Placeholder for an unused CTS pin, e.g. `(tx, rx, rts, NoCts)`
+pub struct NoCts;
+
+unsafe impl<UART> RtsPin<UART> for NoRts {
+    const ENABLED: bool = false;
+}
+unsafe impl<UART> CtsPin<UART> for NoCts {
+    const ENABLED: bool = false;
+}
 
 unsafe impl TxPin<pac::LPUART0> for PTA3<ALT2> {}
 unsafe impl RxPin<pac::LPUART0> for PTA2<ALT2> {}
@@ -253,44 +1510,657 @@ unsafe impl RxPin<pac::LPUART0> for PTC7<ALT3> {}
 unsafe impl RtsPin<pac::LPUART0> for PTC10<ALT3> {}
 unsafe impl CtsPin<pac::LPUART0> for PTC9<ALT3> {}
 
+unsafe impl TxPin<pac::LPUART1> for PTA3<ALT4> {}
+unsafe impl RxPin<pac::LPUART1> for PTA2<ALT4> {}
+unsafe impl RtsPin<pac::LPUART1> for PTA4<ALT4> {}
+unsafe impl CtsPin<pac::LPUART1> for PTA1<ALT4> {}
+
+unsafe impl TxPin<pac::LPUART1> for PTA26<ALT2> {}
+unsafe impl RxPin<pac::LPUART1> for PTA25<ALT2> {}
+unsafe impl RtsPin<pac::LPUART1> for PTA28<ALT2> {}
+unsafe impl CtsPin<pac::LPUART1> for PTA27<ALT2> {}
+
+unsafe impl TxPin<pac::LPUART1> for PTB3<ALT3> {}
+unsafe impl RxPin<pac::LPUART1> for PTB2<ALT3> {}
+unsafe impl RtsPin<pac::LPUART1> for PTB5<ALT3> {}
+unsafe impl CtsPin<pac::LPUART1> for PTB4<ALT3> {}
+
+unsafe impl TxPin<pac::LPUART1> for PTC30<ALT2> {}
+unsafe impl RxPin<pac::LPUART1> for PTC29<ALT2> {}
+unsafe impl RtsPin<pac::LPUART1> for PTD1<ALT2> {}
+unsafe impl CtsPin<pac::LPUART1> for PTD0<ALT2> {}
+
+unsafe impl TxPin<pac::LPUART2> for PTB0<ALT2> {}
+unsafe impl RxPin<pac::LPUART2> for PTB1<ALT2> {}
+unsafe impl RtsPin<pac::LPUART2> for PTA31<ALT2> {}
+unsafe impl CtsPin<pac::LPUART2> for PTA30<ALT2> {}
+
+unsafe impl TxPin<pac::LPUART2> for PTB12<ALT2> {}
+unsafe impl RxPin<pac::LPUART2> for PTB11<ALT2> {}
+unsafe impl RtsPin<pac::LPUART2> for PTB14<ALT2> {}
+unsafe impl CtsPin<pac::LPUART2> for PTB13<ALT2> {}
+
+unsafe impl TxPin<pac::LPUART2> for PTB19<ALT3> {}
+unsafe impl RxPin<pac::LPUART2> for PTB18<ALT3> {}
+unsafe impl RtsPin<pac::LPUART2> for PTB21<ALT3> {}
+unsafe impl CtsPin<pac::LPUART2> for PTB20<ALT3> {}
+
 /// Serial pins - DO NOT IMPLEMENT THIS TRAIT
-pub unsafe trait Pins<UART> {}
+///
+/// # Safety
+///
+/// Implemented by this crate for valid pin sets only.
+///
+/// A set of three pins `(tx, rx, rts)` is an RS-485 port, see [`Rs485`].
+///
+/// A set of four pins enables hardware flow control: the receiver deasserts RTS
+/// when its buffer is full, and the transmitter waits for CTS before each word.
+/// Use [`NoRts`] or [`NoCts`] in place of a pin for one-way flow control.
+///
+/// A [`SingleWire`] TX pin alone is a single-wire port, see [`HalfDuplex`].
+///
+/// Use [`NoTx`] or [`NoRx`] in place of a pin for a port that only receives or
+/// only transmits; only `Read` or `Write` is implemented for such a port.
+pub unsafe trait Pins<UART> {
+    #[doc(hidden)]
+    const TRANSMIT: bool = true;
+    #[doc(hidden)]
+    const RECEIVE: bool = true;
+    #[doc(hidden)]
+    const RTS: bool = false;
+    #[doc(hidden)]
+    const CTS: bool = false;
+    #[doc(hidden)]
+    const DRIVER_ENABLE: bool = false;
+    #[doc(hidden)]
+    const SINGLE_WIRE: bool = false;
+    #[doc(hidden)]
+    type Tx;
+    #[doc(hidden)]
+    type Rx;
+    #[doc(hidden)]
+    fn configure(&self) {}
+    #[doc(hidden)]
+    fn split(self) -> (Self::Tx, Self::Rx);
+    #[doc(hidden)]
+    fn merge(tx: Self::Tx, rx: Self::Rx) -> Self;
+}
+
+/// Serial pins with a transmitter - DO NOT IMPLEMENT THIS TRAIT
+///
+/// # Safety
+///
+/// Implemented by this crate for pin sets with a TX pin only.
+pub unsafe trait TxPins<UART>: Pins<UART> {}
+/// Serial pins with a receiver - DO NOT IMPLEMENT THIS TRAIT
+///
+/// # Safety
+///
+/// Implemented by this crate for pin sets with an RX pin only.
+pub unsafe trait RxPins<UART>: Pins<UART> {}
+
+unsafe impl<UART, TX> Pins<UART> for SingleWire<TX>
+where
+    TX: TxPin<UART> + PinConfig
+{
+    const SINGLE_WIRE: bool = true;
+    type Tx = Self;
+    type Rx = ();
+
+    fn split(self) -> (Self, ()) {
+        (self, ())
+    }
+
+    fn merge(tx: Self, _rx: ()) -> Self {
+        tx
+    }
+
+    fn configure(&self) {
+        // other nodes drive the shared line as well; the pull-up holds it idle
+        self.0.configure_open_drain(true);
+        self.0.configure_pull(Pull::Up);
+    }
+}
 
 unsafe impl<UART, TX, RX> Pins<UART> for (TX, RX) 
 where 
     TX: TxPin<UART>, 
     RX: RxPin<UART>
-{}
+{
+    type Tx = TX;
+    type Rx = RX;
+
+    fn split(self) -> (TX, RX) {
+        self
+    }
+
+    fn merge(tx: TX, rx: RX) -> Self {
+        (tx, rx)
+    }
+}
+
+unsafe impl<UART, TX, RX, DE> Pins<UART> for (TX, RX, DE) 
+where 
+    TX: TxPin<UART>, 
+    RX: RxPin<UART>, 
+    DE: RtsPin<UART>
+{
+    const DRIVER_ENABLE: bool = true;
+    type Tx = (TX, DE);
+    type Rx = RX;
+
+    fn split(self) -> ((TX, DE), RX) {
+        ((self.0, self.2), self.1)
+    }
+
+    fn merge((tx, de): (TX, DE), rx: RX) -> Self {
+        (tx, rx, de)
+    }
+}
 
 unsafe impl<UART, TX, RX, RTS, CTS> Pins<UART> for (TX, RX, RTS, CTS) 
 where 
     TX: TxPin<UART>, 
     RX: RxPin<UART>, 
-    RTS: RxPin<UART>, 
-    CTS: RxPin<UART>
-{}
+    RTS: RtsPin<UART>, 
+    CTS: CtsPin<UART>
+{
+    const RTS: bool = <RTS as RtsPin<UART>>::ENABLED;
+    const CTS: bool = <CTS as CtsPin<UART>>::ENABLED;
+    type Tx = (TX, CTS);
+    type Rx = (RX, RTS);
 
-impl<PINS> embedded_hal::serial::Write<u8> for Serial<pac::LPUART0, PINS> {
-    /// Write error
-    type Error = Error;
+    fn split(self) -> ((TX, CTS), (RX, RTS)) {
+        ((self.0, self.3), (self.1, self.2))
+    }
 
-    /// Writes a single word to the serial interface
-    fn try_write(&mut self, word: u8) -> nb::Result<(), Self::Error> {
-        todo!("{:?}", word)
+    fn merge((tx, cts): (TX, CTS), (rx, rts): (RX, RTS)) -> Self {
+        (tx, rx, rts, cts)
+    }
+}
+
+unsafe impl<UART, TX: TxPin<UART> + PinConfig> TxPins<UART> for SingleWire<TX> {}
+unsafe impl<UART, TX: TxPin<UART> + PinConfig> RxPins<UART> for SingleWire<TX> {}
+unsafe impl<UART, TX: TxPin<UART>, RX: RxPin<UART>> TxPins<UART> for (TX, RX) {}
+unsafe impl<UART, TX: TxPin<UART>, RX: RxPin<UART>> RxPins<UART> for (TX, RX) {}
+unsafe impl<UART, TX: TxPin<UART>, RX: RxPin<UART>, DE: RtsPin<UART>> TxPins<UART> for (TX, RX, DE) {}
+unsafe impl<UART, TX: TxPin<UART>, RX: RxPin<UART>, DE: RtsPin<UART>> RxPins<UART> for (TX, RX, DE) {}
+unsafe impl<UART, TX, RX, RTS, CTS> TxPins<UART> for (TX, RX, RTS, CTS)
+where TX: TxPin<UART>, RX: RxPin<UART>, RTS: RtsPin<UART>, CTS: CtsPin<UART> {}
+unsafe impl<UART, TX, RX, RTS, CTS> RxPins<UART> for (TX, RX, RTS, CTS)
+where TX: TxPin<UART>, RX: RxPin<UART>, RTS: RtsPin<UART>, CTS: CtsPin<UART> {}
+
+macro_rules! serial_word_impl {
+    ($($WORD: ty,)+) => {
+$(
+    impl<UART: Instance, PINS: TxPins<UART>> embedded_hal::serial::Write<$WORD> for Serial<UART, PINS> {
+        /// Write error
+        type Error = Error;
+
+        /// Writes a single word to the serial interface
+        fn try_write(&mut self, word: $WORD) -> nb::Result<(), Self::Error> {
+            write(unsafe { &*UART::ptr() }, u16::from(word))
+        }
+
+        /// Ensures that none of the previously written words are still buffered
+        fn try_flush(&mut self) -> nb::Result<(), Self::Error> {
+            flush(unsafe { &*UART::ptr() })
+        }
     }
 
-    /// Ensures that none of the previously written words are still buffered
-    fn try_flush(&mut self) -> nb::Result<(), Self::Error> {
-        todo!()
+    impl<UART: Instance, PINS: RxPins<UART>> embedded_hal::serial::Read<$WORD> for Serial<UART, PINS> {
+        /// Read error
+        type Error = Error;
+
+        /// Reads a single word from the serial interface
+        fn try_read(&mut self) -> nb::Result<$WORD, Self::Error> {
+            read(unsafe { &*UART::ptr() }).map(|word| word as $WORD)
+        }
+    }
+
+    impl<UART: Instance, PINS: TxPins<UART>> embedded_hal::serial::Write<$WORD> for Transmit<UART, PINS> {
+        /// Write error
+        type Error = Error;
+
+        /// Writes a single word to the serial interface
+        fn try_write(&mut self, word: $WORD) -> nb::Result<(), Self::Error> {
+            write(unsafe { &*UART::ptr() }, u16::from(word))
+        }
+
+        /// Ensures that none of the previously written words are still buffered
+        fn try_flush(&mut self) -> nb::Result<(), Self::Error> {
+            flush(unsafe { &*UART::ptr() })
+        }
     }
+
+    impl<UART: Instance, PINS: RxPins<UART>> embedded_hal::serial::Read<$WORD> for Receive<UART, PINS> {
+        /// Read error
+        type Error = Error;
+
+        /// Reads a single word from the serial interface
+        fn try_read(&mut self) -> nb::Result<$WORD, Self::Error> {
+            read(unsafe { &*UART::ptr() }).map(|word| word as $WORD)
+        }
+    }
+)+
+    };
 }
 
-impl<PINS> embedded_hal::serial::Read<u8> for Serial<pac::LPUART0, PINS> {
-    /// Read error
-    type Error = Error;
+// words of 9 or 10 bits need `u16`; `u8` words are truncated to 8 bits
+serial_word_impl! {
+    u8,
+    u16,
+}
 
-    /// Reads a single word from the serial interface
-    fn try_read(&mut self) -> nb::Result<u8, Self::Error> {
-        todo!()
+impl<UART: Instance, PINS: TxPins<UART>> core::fmt::Write for Serial<UART, PINS> {
+    /// Writes a string, blocking until all bytes are queued
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        write_all(unsafe { &*UART::ptr() }, s.as_bytes());
+        Ok(())
+    }
+}
+
+impl<UART: Instance, PINS: TxPins<UART>> core::fmt::Write for Transmit<UART, PINS> {
+    /// Writes a string, blocking until all bytes are queued
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        write_all(unsafe { &*UART::ptr() }, s.as_bytes());
+        Ok(())
+    }
+}
+
+// Writes a string to an LPUART instance without owning it; the caller must own its transmitter
+#[cfg(any(feature = "log", all(feature = "panic-serial", not(test))))]
+pub(crate) fn write_str<UART: Instance>(s: &str) {
+    write_all(unsafe { &*UART::ptr() }, s.as_bytes())
+}
+
+// Resets an LPUART instance into a polling transmitter with the given baud divisors,
+// regardless of its owner; its pins must be already routed
+#[cfg(all(feature = "panic-serial", not(test)))]
+pub(crate) fn reinit_transmitter<UART: Instance>(plan: BaudPlan, source: Source) {
+    // the clock may be owned by another core
+    UART::enable_clock(&mut unsafe { UART::steal_clock() }, source).ok();
+    let lpuart = unsafe { &*UART::ptr() };
+    // an inverted line keeps its polarity, or the peer would see a break
+    let tx_invert = lpuart.ctrl.read().txinv().bit();
+    lpuart.global.write(|w| w.rst().set_bit());
+    lpuart.global.write(|w| w.rst().clear_bit());
+    // note(unsafe): value is valid from planner
+    lpuart.baud.write(|w| unsafe { w
+        .osr().bits(plan.osr - 1)
+        .sbr().bits(plan.sbr)
+        .bothedge().bit((4..=7).contains(&plan.osr))
+    });
+    lpuart.ctrl.write(|w| w.txinv().bit(tx_invert));
+    lpuart.ctrl.modify(|_, w| w.te().set_bit());
+}
+
+// Waits until the last word is sent
+#[cfg(all(feature = "panic-serial", not(test)))]
+pub(crate) fn flush_transmitter<UART: Instance>() {
+    nb::block!(flush(unsafe { &*UART::ptr() })).ok();
+}
+
+// STAT flags that are cleared by writing one: LBKDIF, RXEDGIF, IDLE, OR, NF, FE, PF, MA1F, MA2F
+const STAT_W1C_MASK: u32 = 0xC01F_C000;
+const STAT_OR: u32 = 1 << 19;
+const STAT_NF: u32 = 1 << 18;
+const STAT_FE: u32 = 1 << 17;
+const STAT_PF: u32 = 1 << 16;
+
+// Clears the given W1C flags in STAT, keeping other flags and configuration bits
+fn clear_flags(uart: &RegisterBlock, flags: u32) {
+    // note(unsafe): only W1C flags in `flags` are written with one
+    uart.stat.modify(|r, w| unsafe { w.bits((r.bits() & !STAT_W1C_MASK) | (flags & STAT_W1C_MASK)) });
+}
+
+const STAT_IDLE: u32 = 1 << 20;
+const STAT_MA1F: u32 = 1 << 15;
+const STAT_MA2F: u32 = 1 << 14;
+const STAT_LBKDIF: u32 = 1 << 31;
+const STAT_RXEDGIF: u32 = 1 << 30;
+
+fn set_interrupt(uart: &RegisterBlock, event: Event, enable: bool) {
+    match event {
+        Event::RxNotEmpty => uart.ctrl.modify(|_, w| w.rie().bit(enable)),
+        Event::TxEmpty => uart.ctrl.modify(|_, w| w.tie().bit(enable)),
+        Event::TxComplete => uart.ctrl.modify(|_, w| w.tcie().bit(enable)),
+        Event::Idle => uart.ctrl.modify(|_, w| w.ilie().bit(enable)),
+        Event::Overrun => uart.ctrl.modify(|_, w| w.orie().bit(enable)),
+        Event::Noise => uart.ctrl.modify(|_, w| w.neie().bit(enable)),
+        Event::Framing => uart.ctrl.modify(|_, w| w.feie().bit(enable)),
+        Event::Parity => uart.ctrl.modify(|_, w| w.peie().bit(enable)),
+        Event::LinBreak => uart.baud.modify(|_, w| w.lbkdie().bit(enable)),
+        Event::RxEdge => uart.baud.modify(|_, w| w.rxedgie().bit(enable)),
+        Event::Match1 => uart.ctrl.modify(|_, w| w.ma1ie().bit(enable)),
+        Event::Match2 => uart.ctrl.modify(|_, w| w.ma2ie().bit(enable)),
+    }
+}
+
+fn is_pending(uart: &RegisterBlock, event: Event) -> bool {
+    let stat = uart.stat.read();
+    match event {
+        Event::RxNotEmpty => stat.rdrf().bit_is_set(),
+        Event::TxEmpty => stat.tdre().bit_is_set(),
+        Event::TxComplete => stat.tc().bit_is_set(),
+        Event::Idle => stat.idle().bit_is_set(),
+        Event::Overrun => stat.or().bit_is_set(),
+        Event::Noise => stat.nf().bit_is_set(),
+        Event::Framing => stat.fe().bit_is_set(),
+        Event::Parity => stat.pf().bit_is_set(),
+        Event::LinBreak => stat.lbkdif().bit_is_set(),
+        Event::RxEdge => stat.rxedgif().bit_is_set(),
+        Event::Match1 => stat.ma1f().bit_is_set(),
+        Event::Match2 => stat.ma2f().bit_is_set(),
+    }
+}
+
+fn clear_event(uart: &RegisterBlock, event: Event) {
+    let flag = match event {
+        Event::RxNotEmpty | Event::TxEmpty | Event::TxComplete => return,
+        Event::Idle => STAT_IDLE,
+        Event::Overrun => STAT_OR,
+        Event::Noise => STAT_NF,
+        Event::Framing => STAT_FE,
+        Event::Parity => STAT_PF,
+        Event::LinBreak => STAT_LBKDIF,
+        Event::RxEdge => STAT_RXEDGIF,
+        Event::Match1 => STAT_MA1F,
+        Event::Match2 => STAT_MA2F,
+    };
+    clear_flags(uart, flag);
+}
+
+// BAUD[MATCFG] values: match address marks, or match any word with idle-line wakeup
+const MATCFG_ADDRESS_MATCH_WAKEUP: u8 = 0;
+const MATCFG_IDLE_MATCH_WAKEUP: u8 = 1;
+
+fn set_match(uart: &RegisterBlock, values: [Option<u16>; 2], matcfg: u8) {
+    // note(unsafe): match values are limited to 10 bits
+    uart.match_.write(|w| unsafe { w
+        .ma1().bits(values[0].unwrap_or(0) & 0x3FF)
+        .ma2().bits(values[1].unwrap_or(0) & 0x3FF)
+    });
+    uart.baud.modify(|_, w| w
+        .maen1().bit(values[0].is_some())
+        .maen2().bit(values[1].is_some())
+        .matcfg().bits(matcfg)
+    );
+}
+
+// Runs `f` with transmitter and receiver disabled, after words in the transmitter are sent
+fn reconfigure(uart: &RegisterBlock, f: impl FnOnce()) {
+    let ctrl = uart.ctrl.read();
+    let (te, re) = (ctrl.te().bit(), ctrl.re().bit());
+    if te {
+        while uart.stat.read().tc().bit_is_clear() {}
+    }
+    uart.ctrl.modify(|_, w| w.te().clear_bit().re().clear_bit());
+    while uart.ctrl.read().te().bit_is_set() || uart.ctrl.read().re().bit_is_set() {}
+    f();
+    uart.ctrl.modify(|_, w| w.te().bit(te).re().bit(re));
+}
+
+fn tx_ready(uart: &RegisterBlock) -> bool {
+    if uart.fifo.read().txfe().bit_is_set() {
+        // room left in transmit FIFO, regardless of watermark
+        (uart.water.read().txcount().bits() as u32) < (1 << uart.param.read().txfifo().bits())
+    } else {
+        uart.stat.read().tdre().bit_is_set()
+    }
+}
+
+fn write(uart: &RegisterBlock, word: u16) -> nb::Result<(), Error> {
+    if !tx_ready(uart) {
+        return Err(nb::Error::WouldBlock);
+    }
+    // note(unsafe): DATA register accepts any word value
+    uart.data.write(|w| unsafe { w.bits(word as u32) });
+    Ok(())
+}
+
+fn write_all(uart: &RegisterBlock, bytes: &[u8]) {
+    for &byte in bytes {
+        // writing data never fails
+        nb::block!(write(uart, byte as u16)).ok();
+    }
+}
+
+fn write_break(uart: &RegisterBlock) -> nb::Result<(), Error> {
+    if !tx_ready(uart) {
+        return Err(nb::Error::WouldBlock);
+    }
+    // FRETSC with all data bits clear queues a break character
+    uart.data.write(|w| w.fretsc().set_bit());
+    Ok(())
+}
+
+fn flush(uart: &RegisterBlock) -> nb::Result<(), Error> {
+    if uart.stat.read().tc().bit_is_clear() {
+        return Err(nb::Error::WouldBlock);
+    }
+    Ok(())
+}
+
+fn flush_tx_fifo(uart: &RegisterBlock) {
+    uart.fifo.modify(|_, w| w.txflush().set_bit());
+}
+
+fn flush_rx_fifo(uart: &RegisterBlock) {
+    uart.fifo.modify(|_, w| w.rxflush().set_bit());
+}
+
+// Mask of data bits in a received word, without the parity bit
+fn data_mask(uart: &RegisterBlock) -> u16 {
+    let ctrl = uart.ctrl.read();
+    let bits = if uart.baud.read().m10().bit_is_set() {
+        10
+    } else if ctrl.m().bit_is_set() {
+        9
+    } else if ctrl.m7().bit_is_set() {
+        7
+    } else {
+        8
+    } - ctrl.pe().bit() as u16;
+    (1 << bits) - 1
+}
+
+fn read(uart: &RegisterBlock) -> nb::Result<u16, Error> {
+    let stat = uart.stat.read();
+    // error flags are set together with RDRF for the offending word
+    let (error, flag) = if stat.or().bit_is_set() {
+        (Error::Overrun, STAT_OR)
+    } else if stat.pf().bit_is_set() {
+        (Error::Parity, STAT_PF)
+    } else if stat.fe().bit_is_set() {
+        (Error::Framing, STAT_FE)
+    } else if stat.nf().bit_is_set() {
+        (Error::Noise, STAT_NF)
+    } else if stat.rdrf().bit_is_set() || uart.fifo.read().rxempt().bit_is_clear() {
+        // receive FIFO may hold words below watermark
+        return Ok(uart.data.read().bits() as u16 & data_mask(uart));
+    } else {
+        return Err(nb::Error::WouldBlock);
+    };
+    clear_flags(uart, flag);
+    if flag != STAT_OR {
+        // drop the word received with error; the word before an overrun is still valid
+        uart.data.read();
+    }
+    Err(nb::Error::Other(error))
+}
+
+fn read_until_idle(uart: &RegisterBlock, buf: &mut [u8]) -> Result<usize, Error> {
+    // an idle flag from an earlier burst would end this one early
+    if uart.fifo.read().rxempt().bit_is_set() {
+        clear_flags(uart, STAT_IDLE);
+    }
+    let mut len = 0;
+    while len < buf.len() {
+        // the idle flag is sampled first, so no word can arrive unnoticed before it
+        let idle = uart.stat.read().idle().bit_is_set();
+        match read(uart) {
+            Ok(word) => {
+                buf[len] = word as u8;
+                len += 1;
+            }
+            Err(nb::Error::WouldBlock) if idle && len > 0 => break,
+            Err(nb::Error::WouldBlock) => {}
+            Err(nb::Error::Other(error)) => return Err(error),
+        }
+    }
+    if uart.fifo.read().rxempt().bit_is_set() {
+        clear_flags(uart, STAT_IDLE);
+    }
+    Ok(len)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CLOCKS_HZ: [u32; 8] = [
+        1_000_000, 8_000_000, 12_000_000, 16_000_000, 24_000_000, 32_768_000, 48_000_000, 72_000_000,
+    ];
+    const BAUDRATES: [u32; 12] = [
+        1_200, 2_400, 4_800, 9_600, 19_200, 38_400, 57_600, 115_200, 230_400, 460_800, 921_600, 1_000_000,
+    ];
+
+    fn try_plan(clock: u32, baudrate: u32, tolerance_ppm: u32) -> Result<BaudPlan, BaudError> {
+        plan_baudrate(Hertz::new(clock), Baud::new(baudrate), tolerance_ppm)
+    }
+
+    // relative error of a divisor as a fraction (numerator, denominator)
+    fn relative_error(clock: u32, baudrate: u32, osr: u32, sbr: u32) -> (u128, u128) {
+        let divisor = osr as u128 * sbr as u128;
+        let target = baudrate as u128 * divisor;
+        ((clock as i128 - target as i128).unsigned_abs(), target)
+    }
+
+    fn check_invariants(clock: u32, baudrate: u32, tolerance_ppm: u32, plan: &BaudPlan) {
+        assert!((4..=32).contains(&plan.osr), "{:?}", plan);
+        assert!((1..=8191).contains(&plan.sbr), "{:?}", plan);
+        assert!(plan.error_ppm.unsigned_abs() <= tolerance_ppm, "{:?}", plan);
+        let divisor = plan.osr as u64 * plan.sbr as u64;
+        let achieved = (clock as u64 + divisor / 2) / divisor;
+        assert_eq!(plan.baudrate.0 as u64, achieved, "{:?}", plan);
+        let error_ppm = (clock as i64 * 1_000_000 - baudrate as i64 * divisor as i64 * 1_000_000)
+            / (baudrate as i64 * divisor as i64);
+        assert_eq!(plan.error_ppm as i64, error_ppm, "{:?}", plan);
+    }
+
+    // no divisor in the whole range has a smaller error
+    fn check_optimal(clock: u32, baudrate: u32, plan: &BaudPlan) {
+        let (best_num, best_den) = relative_error(clock, baudrate, plan.osr as u32, plan.sbr as u32);
+        for osr in 4..=32 {
+            for sbr in 1..=8191 {
+                let (num, den) = relative_error(clock, baudrate, osr, sbr);
+                assert!(num * best_den >= best_num * den,
+                    "osr {} sbr {} is better than {:?}", osr, sbr, plan);
+            }
+        }
+    }
+
+    #[test]
+    fn exact_divisors() {
+        let plan = try_plan(72_000_000, 115_200, 0).unwrap();
+        assert_eq!((plan.osr, plan.sbr, plan.baudrate.0, plan.error_ppm), (25, 25, 115_200, 0));
+        let plan = try_plan(48_000_000, 9_600, 0).unwrap();
+        assert_eq!((plan.osr, plan.sbr, plan.error_ppm), (25, 200, 0));
+        let plan = try_plan(48_000_000, 1_000_000, 0).unwrap();
+        assert_eq!((plan.osr, plan.sbr, plan.error_ppm), (24, 2, 0));
+    }
+
+    #[test]
+    fn inexact_divisor() {
+        let plan = try_plan(8_000_000, 115_200, DEFAULT_BAUD_TOLERANCE_PPM).unwrap();
+        assert_eq!((plan.osr, plan.sbr, plan.baudrate.0, plan.error_ppm), (23, 3, 115_942, 6_441));
+    }
+
+    #[test]
+    fn tolerance_is_inclusive() {
+        assert!(try_plan(8_000_000, 115_200, 6_441).is_ok());
+        assert_eq!(try_plan(8_000_000, 115_200, 6_440), Err(BaudError::Tolerance { error_ppm: 6_441 }));
+    }
+
+    #[test]
+    fn zero_inputs() {
+        assert_eq!(try_plan(0, 115_200, DEFAULT_BAUD_TOLERANCE_PPM), Err(BaudError::Zero));
+        assert_eq!(try_plan(72_000_000, 0, DEFAULT_BAUD_TOLERANCE_PPM), Err(BaudError::Zero));
+    }
+
+    #[test]
+    fn baudrate_too_low() {
+        // 72 MHz / (32 * 8191) is about 274.69 Bd, the lowest rate
+        let plan = try_plan(72_000_000, 274, DEFAULT_BAUD_TOLERANCE_PPM).unwrap();
+        assert_eq!((plan.osr, plan.sbr, plan.baudrate.0, plan.error_ppm), (32, 8191, 275, 2_524));
+        let plan = try_plan(72_000_000, 270, u32::MAX).unwrap();
+        assert_eq!((plan.osr, plan.sbr, plan.error_ppm), (32, 8191, 17_376));
+        assert!(try_plan(72_000_000, 275, u32::MAX).is_ok());
+        // more than 37% off even with the largest divisor
+        assert_eq!(try_plan(72_000_000, 200, DEFAULT_BAUD_TOLERANCE_PPM), Err(BaudError::OutOfRange));
+        assert_eq!(try_plan(u32::MAX, 1, u32::MAX), Err(BaudError::OutOfRange));
+    }
+
+    #[test]
+    fn baudrate_too_high() {
+        // 8 MHz / 4 is the highest rate, 2 MBd
+        assert_eq!(try_plan(8_000_000, 3_000_000, DEFAULT_BAUD_TOLERANCE_PPM),
+            Err(BaudError::Tolerance { error_ppm: -333_333 }));
+        assert!(try_plan(8_000_000, 2_000_000, 0).is_ok());
+    }
+
+    #[test]
+    fn common_clocks_and_baudrates() {
+        for &clock in CLOCKS_HZ.iter() {
+            for &baudrate in BAUDRATES.iter() {
+                match try_plan(clock, baudrate, DEFAULT_BAUD_TOLERANCE_PPM) {
+                    Ok(plan) => {
+                        check_invariants(clock, baudrate, DEFAULT_BAUD_TOLERANCE_PPM, &plan);
+                        check_optimal(clock, baudrate, &plan);
+                    }
+                    // only rates near the limit of slow clocks may fail
+                    Err(BaudError::Tolerance { .. }) => assert!(clock / baudrate < 64),
+                    Err(error) => panic!("{} Hz, {} Bd: {:?}", clock, baudrate, error),
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn random_clocks_and_baudrates() {
+        // xorshift, so the sweep is the same in every run
+        let mut state = 0x2545_F491_u32;
+        let mut next = move || {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            state
+        };
+        for _ in 0..64 {
+            let clock = 1_000_000 + next() % 99_000_000;
+            let baudrate = 300 + next() % (clock / 4);
+            let tolerance_ppm = next() % 50_000;
+            match try_plan(clock, baudrate, tolerance_ppm) {
+                Ok(plan) => {
+                    check_invariants(clock, baudrate, tolerance_ppm, &plan);
+                    check_optimal(clock, baudrate, &plan);
+                }
+                Err(BaudError::Tolerance { error_ppm }) => {
+                    assert!(error_ppm.unsigned_abs() > tolerance_ppm);
+                    // the same plan is accepted without a tolerance
+                    let plan = try_plan(clock, baudrate, u32::MAX).unwrap();
+                    assert_eq!(plan.error_ppm, error_ppm);
+                    check_optimal(clock, baudrate, &plan);
+                }
+                Err(BaudError::OutOfRange) => {
+                    // the largest divisor is still too fast
+                    let (num, den) = relative_error(clock, baudrate, 32, 8191);
+                    assert!(clock as u64 > 32 * 8191 * baudrate as u64);
+                    assert!(num * 1_000_000 > den * tolerance_ppm as u128);
+                }
+                Err(BaudError::Zero) => unreachable!(),
+            }
+        }
     }
 }
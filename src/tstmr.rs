@@ -9,6 +9,11 @@
 
 use crate::pac::TSTMRA;
 
+/// Frequency of the time stamp counter in hertz
+///
+/// TSTMR is clocked by a 1 MHz clock, so one tick is one microsecond.
+pub const FREQUENCY_HZ: u32 = 1_000_000;
+
 /// Get the 56-bit timestamp value from TSTMRA registers
 /// 
 /// The high 8 bits would always be zero.
@@ -19,3 +24,12 @@ pub fn timestamp() -> u64 {
     let low = tstmra.low.read().bits();
     ((high as u64) << 32) | (low as u64)
 }
+
+/// Busy wait for at least `us` microseconds
+///
+/// The delay is measured with the time stamp counter, so it does not depend
+/// on core clock frequency.
+pub fn delay_us(us: u32) {
+    let start = timestamp();
+    while timestamp().wrapping_sub(start) < us as u64 {}
+}